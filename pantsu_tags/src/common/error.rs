@@ -26,6 +26,12 @@ pub enum Error {
     #[error("Failed to parse html, maybe the website layout changed?")]
     HtmlParseError,
 
+    #[error("Failed to parse api response: {0}")]
+    ApiParseError(String),
+
+    #[error("The post of the sauce was deleted")]
+    PostDeleted,
+
     #[error("Sauce value '{0}' is not a valid URL")]
     InvalidSauce(String),
     
@@ -35,6 +41,22 @@ pub enum Error {
     #[error("Sauce type is not valid: {0}")]
     InvalidSauceType(String),
 
+    #[error("No tag provider supports the sauce: {0}")]
+    UnsupportedSauce(String),
+
+    // model tagger errors
+    #[error("No tagger model configured")]
+    NoTaggerModel,
+
+    #[error("Failed to load tagger model '{0}': {1}")]
+    ModelLoadError(String, String),
+
+    #[error("Invalid tagger labels '{0}': {1}")]
+    InvalidModelLabels(String, String),
+
+    #[error("Failed to run tagger model: {0}")]
+    ModelInferenceError(String),
+
     // pantsu tag database errors
     #[error("Primary key constraint error: {0}")]
     SQLPrimaryKeyError(#[source] rusqlite::Error),
@@ -42,12 +64,24 @@ pub enum Error {
     #[error("Failed underlying SQLite call: {0}")]
     SQLError(#[from] rusqlite::Error),
 
+    #[error("Invalid sauce judge policy in database: {0}")]
+    InvalidJudgePolicy(#[source] serde_json::Error),
+
     #[error("Cannot convert invalid tag type '{0}' to enum variant of PantsuTagType, valid types: artist, source, character, general, rating, custom")]
     InvalidTagType(String),
 
-    #[error("Cannot convert invalid tag author '{0}' to enum variant of PantsuTagAuthor, valid types: gelbooru, user")]
+    #[error("Cannot convert invalid tag author '{0}' to enum variant of PantsuTagAuthor, valid types: gelbooru, danbooru, model, user")]
     InvalidTagAuthor(String),
 
+    #[error("Cannot convert invalid rating '{0}' to enum variant of Rating, valid ratings: general, sensitive, questionable, explicit")]
+    InvalidRating(String),
+
+    #[error("Cannot convert invalid image source kind '{0}' to enum variant of ImageSourceKind, valid kinds: booru_post, original, artist_page")]
+    InvalidImageSourceKind(String),
+
+    #[error("Invalid image source format '{0}', expected 'kind=url'")]
+    InvalidImageSourceFormat(String),
+
     #[error("Cannot convert tag string '{0}' to PantsuTag, valid format: <type>:<name>")]
     InvalidTagFormat(String),
 
@@ -72,6 +106,9 @@ pub enum Error {
     #[error("Image not found in database: {0}")]
     ImageNotFoundInDB(String),
 
+    #[error("Cannot merge image {0} into itself")]
+    MergeIntoSelf(String),
+
     #[error("{0}. Please update program to the newest version.")]
     ProgramOutdated(String),
 
@@ -2,7 +2,9 @@ use std::fmt;
 
 use chrono::NaiveDateTime;
 
-use crate::{ImageHandle, Sauce, Error, Result, sauce::{NOT_EXISTING_FLAG, NOT_CHECKED_FLAG}};
+use crate::{ImageHandle, Sauce, Error, Result, sauce::{UNSURE_FLAG, NOT_EXISTING_FLAG, NOT_CHECKED_FLAG}};
+use crate::common::image_source::ImageSource;
+use crate::common::rating::Rating;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct ImageInfo {
@@ -11,13 +13,25 @@ pub struct ImageInfo {
     image_res: (u32, u32),
     date_added: NaiveDateTime,
     date_modified: NaiveDateTime,
+    sources: Vec<ImageSource>,
+    rating: Option<Rating>,
 }
 
 pub(crate) const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 impl ImageInfo {
     pub(crate) fn new(image_handle: ImageHandle, image_sauce: Sauce, image_res: (u32, u32), date_added: NaiveDateTime, date_modified: NaiveDateTime) -> Self {
-        ImageInfo { image_handle, image_sauce, image_res, date_added, date_modified }
+        ImageInfo { image_handle, image_sauce, image_res, date_added, date_modified, sources: Vec::new(), rating: None }
+    }
+
+    pub(crate) fn with_sources(mut self, sources: Vec<ImageSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub(crate) fn with_rating(mut self, rating: Option<Rating>) -> Self {
+        self.rating = rating;
+        self
     }
 
     pub fn get_image(&self) -> &ImageHandle {
@@ -40,25 +54,38 @@ impl ImageInfo {
         &self.date_modified
     }
 
+    // other places the image can be found, e.g. the original post of the artist
+    pub fn get_sources(&self) -> &[ImageSource] {
+        &self.sources
+    }
+
+    // taken from the rating tag of the image, None if it has no known rating
+    pub fn get_rating(&self) -> Option<Rating> {
+        self.rating
+    }
+
     pub fn serialize(&self) -> String {
-        format!("{};{};{};{};{};{}",
+        format!("{};{};{};{};{};{};{}",
             self.image_handle.get_filename(),
             self.image_sauce,
             self.image_res.0,
             self.image_res.1,
             self.date_added.format(DATE_TIME_FORMAT),
-            self.date_modified.format(DATE_TIME_FORMAT)
+            self.date_modified.format(DATE_TIME_FORMAT),
+            self.sources.iter().map(|s| s.serialize()).collect::<Vec<String>>().join(" ")
         )
     }
 
+    // exports of older versions end after the modification date and have no sources
     pub fn deserialize(text: &str) -> Result<Self> {
-        let split = text.splitn(6, ';').collect::<Vec<_>>();
-        if split.len()!=6 {
+        let split = text.splitn(7, ';').collect::<Vec<_>>();
+        if split.len()<6 {
             Err(Error::InvalidImportFileLineFormat(text.to_string()))
         } else {
             Ok(ImageInfo {
                 image_handle: ImageHandle::new(split[0].to_string())?,
                 image_sauce: match split[1] {
+                    UNSURE_FLAG => Sauce::Unsure,
                     NOT_EXISTING_FLAG => Sauce::NotExisting,
                     NOT_CHECKED_FLAG => Sauce::NotChecked,
                     other => Sauce::Match(crate::sauce::url_from_str(other)?)
@@ -69,6 +96,10 @@ impl ImageInfo {
                 ),
                 date_added: NaiveDateTime::parse_from_str(split[4], DATE_TIME_FORMAT).or_else(|_| Err(Error::InvalidImportFileLineFormat(text.to_string())))?,
                 date_modified: NaiveDateTime::parse_from_str(split[5], DATE_TIME_FORMAT).or_else(|_| Err(Error::InvalidImportFileLineFormat(text.to_string())))?,
+                sources: split.get(6).unwrap_or(&"").split_whitespace()
+                    .map(ImageSource::deserialize)
+                    .collect::<Result<Vec<ImageSource>>>()?,
+                rating: None,
             })
         }
     }
@@ -76,6 +107,10 @@ impl ImageInfo {
 
 impl fmt::Display for ImageInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}, res=({}x{}), sauce={}, date={}", self.image_handle.get_filename(), &self.image_res.0, self.image_res.1, self.image_sauce, self.date_added.format(DATE_TIME_FORMAT))
+        write!(f, "{}, res=({}x{}), sauce={}, date={}", self.image_handle.get_filename(), &self.image_res.0, self.image_res.1, self.image_sauce, self.date_added.format(DATE_TIME_FORMAT))?;
+        match self.rating {
+            Some(rating) => write!(f, ", rating={}", rating),
+            None => Ok(()),
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use crate::common;
+use crate::common::error::{Result, Error};
+
+// a place the image can be found, besides the sauce that is stored with the image itself
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct ImageSource {
+    pub url: String,
+    pub kind: ImageSourceKind,
+}
+
+impl ImageSource {
+    pub fn new(url: String, kind: ImageSourceKind) -> Self {
+        ImageSource { url, kind }
+    }
+
+    pub fn serialize(&self) -> String {
+        format!("{}={}", self.kind.serialize(), common::encode_export_separators(&self.url))
+    }
+
+    pub fn deserialize(text: &str) -> Result<Self> {
+        match text.split_once('=') {
+            Some((kind, url)) => Ok(ImageSource { kind: ImageSourceKind::deserialize(kind)?, url: url.to_string() }),
+            None => Err(Error::InvalidImageSourceFormat(text.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ImageSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.url)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageSourceKind {
+    BooruPost,
+    Original,       // e.g. the pixiv, twitter or fanbox post of the artist
+    ArtistPage,
+}
+
+impl ImageSourceKind {
+    pub fn serialize(&self) -> String {
+        let str = match self {
+            ImageSourceKind::BooruPost => "booru_post",
+            ImageSourceKind::Original => "original",
+            ImageSourceKind::ArtistPage => "artist_page",
+        };
+        String::from(str)
+    }
+
+    pub fn deserialize(text: &str) -> Result<Self> {
+        match text {
+            "booru_post" => Ok(ImageSourceKind::BooruPost),
+            "original" => Ok(ImageSourceKind::Original),
+            "artist_page" => Ok(ImageSourceKind::ArtistPage),
+            other => Err(Error::InvalidImageSourceKind(String::from(other)))
+        }
+    }
+
+    // The source field of booru posts sometimes only links the profile of the artist instead of the post
+    pub fn of_original_source(url: &str) -> Self {
+        let url = url.to_lowercase();
+        let is_artist_page = url.contains("pixiv.net/users/") || url.contains("member.php")
+            || ((url.contains("twitter.com/") || url.contains("x.com/")) && !url.contains("/status/"));
+        if is_artist_page { ImageSourceKind::ArtistPage } else { ImageSourceKind::Original }
+    }
+}
+
+impl fmt::Display for ImageSourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serialize())
+    }
+}
+
+impl FromStr for ImageSourceKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::deserialize(s)
+    }
+}
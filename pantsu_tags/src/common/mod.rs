@@ -4,10 +4,43 @@ pub mod error;
 pub mod pantsu_tag;
 pub mod image_handle;
 pub mod image_info;
+pub mod image_source;
+pub mod rating;
 pub mod tmp_dir;
 
 pub use tmp_dir::tmp_dir_async as tmp_dir_async;
 
 pub fn get_path(path: &Path) -> String {
     String::from(path.to_str().unwrap_or("cannot display path"))
+}
+
+// number of single character insertions, deletions and substitutions to turn a into b
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + (a_char != b_char) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// escapes the glob wildcards of sqlite, so the text only matches itself
+pub(crate) fn escape_glob(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '*' | '?' | '[' => format!("[{}]", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+// percent-encodes the separators of the export format in urls, the urls stay equivalent
+pub(crate) fn encode_export_separators(url: &str) -> String {
+    url.replace(' ', "%20").replace(',', "%2C").replace(';', "%3B")
 }
\ No newline at end of file
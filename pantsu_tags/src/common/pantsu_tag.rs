@@ -1,8 +1,12 @@
 use std::fmt;
 use std::fmt::Formatter;
 use std::str::FromStr;
+use std::sync::RwLock;
 use chrono::{NaiveDateTime};
 use enum_iterator::IntoEnumIterator;
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+use crate::common;
 use crate::common::error::{Result, Error};
 
 use super::image_info::DATE_TIME_FORMAT;
@@ -15,7 +19,7 @@ pub struct PantsuTag {
 
 impl PantsuTag {
     pub fn new(tag_name: String, tag_type: PantsuTagType) -> Self {
-        PantsuTag { tag_name, tag_type }
+        PantsuTag { tag_name: normalize_tag_name(&tag_name), tag_type }
     }
     
     pub fn display_vec(vec: &Vec<PantsuTag>) -> String {
@@ -29,7 +33,7 @@ impl PantsuTag {
     pub fn deserialize(text: &str) -> Result<Self> {
         let split = text.split_once(':');
         match split {
-            Some((tag_type, tag_name)) => Ok(PantsuTag { tag_type: PantsuTagType::deserialize(tag_type)?, tag_name: tag_name.to_string() }),
+            Some((tag_type, tag_name)) => Ok(PantsuTag::new(tag_name.to_string(), PantsuTagType::deserialize(tag_type)?)),
             None => Err(Error::InvalidTagFormat(text.to_string()))
         }
     }
@@ -47,26 +51,86 @@ impl FromStr for PantsuTag {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let split = s.split_once(':');
         match split {
-            Some((tag_type, tag_name)) => Ok(PantsuTag { tag_type: PantsuTagType::from_str(tag_type)?, tag_name: tag_name.to_string() }),
+            Some((tag_type, tag_name)) => Ok(PantsuTag::new(tag_name.to_string(), PantsuTagType::from_str(tag_type)?)),
             None => Err(Error::InvalidTagFormat(String::from(s))),
         }
     }
 }
 
+lazy_static! {
+    static ref TAG_NORMALIZER: RwLock<TagNormalizer> = RwLock::new(TagNormalizer::default());
+}
+
+/// How tag names are normalized before they are compared or stored, so that
+/// e.g. "Stuffed_Dinosaur" and "stuffed dinosaur" end up as the same tag
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagNormalizer {
+    pub lowercase: bool,
+    pub underscores_to_spaces: bool,
+    // trims the name and collapses runs of whitespace inside it to one space
+    pub trim_whitespace: bool,
+}
+
+impl TagNormalizer {
+    pub fn normalize(&self, tag_name: &str) -> String {
+        let mut name = tag_name.to_string();
+        if self.underscores_to_spaces {
+            name = name.replace('_', " ");
+        }
+        if self.trim_whitespace {
+            name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if self.lowercase {
+            name = name.to_lowercase();
+        }
+        name
+    }
+}
+
+impl Default for TagNormalizer {
+    fn default() -> Self {
+        TagNormalizer {
+            lowercase: true,
+            underscores_to_spaces: true,
+            trim_whitespace: true,
+        }
+    }
+}
+
+/// Sets the normalizer that is applied to all tags created from now on
+pub fn set_tag_normalizer(normalizer: TagNormalizer) {
+    *TAG_NORMALIZER.write().unwrap_or_else(|e| e.into_inner()) = normalizer;
+}
+
+pub fn get_tag_normalizer() -> TagNormalizer {
+    TAG_NORMALIZER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub(crate) fn normalize_tag_name(tag_name: &str) -> String {
+    TAG_NORMALIZER.read().unwrap_or_else(|e| e.into_inner()).normalize(tag_name)
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct PantsuTagInfo {
     pub tag: PantsuTag,
-    pub tag_author: PantsuTagAuthor,
-    pub date_added: NaiveDateTime
+    pub tag_author: PantsuTagAuthor,   // for tags of a booru this is the provider that found them
+    pub date_added: NaiveDateTime,
+    pub sauce: Option<String>,          // the sauce link the tag was found on, None for tags of the user
 }
 
 impl PantsuTagInfo {
     pub fn serialize(&self) -> String {
-        format!("{};{};{}", self.tag.serialize(), self.tag_author.serialize(), self.date_added.format(DATE_TIME_FORMAT))
+        format!("{};{};{};{}", self.tag.serialize(), self.tag_author.serialize(), self.date_added.format(DATE_TIME_FORMAT),
+            self.sauce.as_deref().map(common::encode_export_separators).unwrap_or_default())
     }
 
+    // exports of older versions end with the date and have no sauce field, a sauce is never a date
     pub fn deserialize(text: &str) -> Result<Self> {
-        let reg = text.rsplitn(3, ';').collect::<Vec<_>>();
+        let (fields, sauce) = match text.rsplit_once(';') {
+            Some((fields, sauce)) if NaiveDateTime::parse_from_str(sauce, DATE_TIME_FORMAT).is_err() => (fields, sauce),
+            _ => (text, ""),
+        };
+        let reg = fields.rsplitn(3, ';').collect::<Vec<_>>();
         if reg.len()<3 {
             Err(Error::InvalidTagFormat(text.to_string()))
         } else {
@@ -74,12 +138,57 @@ impl PantsuTagInfo {
                 tag: PantsuTag::deserialize(reg[2])?,
                 tag_author: PantsuTagAuthor::deserialize(reg[1])?,
                 date_added: NaiveDateTime::parse_from_str(reg[0], DATE_TIME_FORMAT)
-                    .or_else(|e| Err(Error::InvalidTagFormat(e.to_string())))?
+                    .map_err(|e| Error::InvalidTagFormat(e.to_string()))?,
+                sauce: Some(sauce.to_string()).filter(|sauce| !sauce.is_empty()),
             })
         }
     }
 }
 
+// tags that were added to or removed from a tag list, e.g. by the booru of a sauce
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PantsuTagDiff {
+    pub added: Vec<PantsuTag>,
+    pub removed: Vec<PantsuTag>,
+}
+
+impl PantsuTagDiff {
+    pub fn new(old_tags: &[PantsuTag], new_tags: &[PantsuTag]) -> Self {
+        PantsuTagDiff {
+            added: new_tags.iter().filter(|t| !old_tags.contains(t)).cloned().collect(),
+            removed: old_tags.iter().filter(|t| !new_tags.contains(t)).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+// a tag with the number of images it is used on
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct PantsuTagCount {
+    pub tag: PantsuTag,
+    pub count: usize,
+}
+
+// a tag that is used together with the tags of a query
+#[derive(Debug, PartialEq, Clone)]
+pub struct RelatedPantsuTag {
+    pub tag: PantsuTag,
+    pub cooccurrences: usize,   // images of the query with this tag
+    pub count: usize,           // all images with this tag
+    pub frequency: f32,         // share of the images of the query with this tag
+}
+
+// a tag that was not renamed on an image, as the image already has a tag of another type with the normalized name
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TagNameCollision {
+    pub filename: String,
+    pub tag: PantsuTag,
+    pub normalized_name: String,
+}
+
 /*impl fmt::Display for PantsuTagInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}({})", self.tag.to_string(), self.tag_author)
@@ -140,6 +249,8 @@ impl FromStr for PantsuTagType {
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum PantsuTagAuthor {
     Gelbooru,
+    Danbooru,
+    Model,
     User
 }
 
@@ -147,6 +258,8 @@ impl PantsuTagAuthor {
     pub fn serialize(&self) -> String {
         let str = match self {
             PantsuTagAuthor::Gelbooru => "gelbooru",
+            PantsuTagAuthor::Danbooru => "danbooru",
+            PantsuTagAuthor::Model => "model",
             PantsuTagAuthor::User => "user",
         };
         String::from(str)
@@ -155,6 +268,8 @@ impl PantsuTagAuthor {
     pub fn deserialize(text: &str) -> Result<Self> {
         match text {
             "gelbooru" => Ok(PantsuTagAuthor::Gelbooru),
+            "danbooru" => Ok(PantsuTagAuthor::Danbooru),
+            "model" => Ok(PantsuTagAuthor::Model),
             "user" => Ok(PantsuTagAuthor::User),
             other => Err(Error::InvalidTagAuthor(String::from(other)))
         }
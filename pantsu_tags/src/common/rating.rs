@@ -0,0 +1,67 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use serde_derive::{Deserialize, Serialize};
+use crate::common::error::{Result, Error};
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+
+// content rating of an image, ordered from safe to explicit.
+// It is stored as the rating tag of the image, so it can be searched and edited like any other tag
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    General,
+    Sensitive,
+    Questionable,
+    Explicit,
+}
+
+impl Rating {
+    pub fn serialize(&self) -> String {
+        let str = match self {
+            Rating::General => "general",
+            Rating::Sensitive => "sensitive",
+            Rating::Questionable => "questionable",
+            Rating::Explicit => "explicit",
+        };
+        String::from(str)
+    }
+
+    // ignores the case of the rating, as the boorus capitalise them differently.
+    // "safe" is the name gelbooru used for general before
+    pub fn deserialize(text: &str) -> Result<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "general" | "safe" => Ok(Rating::General),
+            "sensitive" => Ok(Rating::Sensitive),
+            "questionable" => Ok(Rating::Questionable),
+            "explicit" => Ok(Rating::Explicit),
+            _ => Err(Error::InvalidRating(text.to_string())),
+        }
+    }
+
+    pub fn to_tag(&self) -> PantsuTag {
+        PantsuTag::new(self.serialize(), PantsuTagType::Rating)
+    }
+
+    // None for tags of other types and rating tags with an unknown name
+    pub fn from_tag(tag: &PantsuTag) -> Option<Self> {
+        match tag.tag_type {
+            PantsuTagType::Rating => Rating::deserialize(&tag.tag_name).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serialize())
+    }
+}
+
+impl FromStr for Rating {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::deserialize(s)
+    }
+}
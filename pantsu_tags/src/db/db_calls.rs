@@ -1,11 +1,13 @@
-use chrono::{Utc};
+use chrono::{NaiveDateTime, Utc};
 use rusqlite::{Connection, ffi, params, Transaction};
 use crate::common::error::Result;
 use crate::common::image_handle::ImageHandle;
 use crate::common::image_info::{ImageInfo, DATE_TIME_FORMAT};
-use crate::common::pantsu_tag::{PantsuTag, PantsuTagType, PantsuTagAuthor, PantsuTagInfo};
+use crate::common::image_source::ImageSource;
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagType, PantsuTagAuthor, PantsuTagCount, PantsuTagInfo, RelatedPantsuTag, TagNameCollision, TagNormalizer};
+use std::collections::HashMap;
 use crate::db::{SauceType, sqlite_statements};
-use crate::{Error, Sauce, sauce};
+use crate::{Error, Sauce, SauceCandidate, SauceJudgePolicy, SauceLookup, SauceMatch, sauce};
 
 use super::sort::{ImageSortOption, SortOrder, TagSortOption};
 
@@ -44,12 +46,62 @@ pub(crate) fn add_image_to_images(transaction: &Transaction, image: &ImageHandle
     }
 }
 
-pub(crate) fn add_tags_to_image(transaction: &Transaction, image: &ImageHandle, tags: &Vec<&PantsuTag>, tag_author: &PantsuTagAuthor) -> Result<()> {
+pub(crate) fn add_tags_to_image(transaction: &Transaction, image: &ImageHandle, tags: &Vec<&PantsuTag>, tag_author: &PantsuTagAuthor, sauce: Option<&str>) -> Result<()> {
     let mut add_tag_stmt = transaction.prepare(sqlite_statements::INSERT_TAG_FOR_IMAGE)?;
     let now = Utc::now().naive_utc().format(DATE_TIME_FORMAT).to_string();
+    let found_on_sauce = sauce.is_some() && *tag_author != PantsuTagAuthor::User;
     let tag_author = tag_author.serialize();
     for &tag in tags {
-        add_tag_stmt.execute(params![image.get_filename(), tag.tag_name, tag.tag_type.serialize(), &tag_author, &now])?;
+        add_tag_stmt.execute(params![image.get_filename(), tag.tag_name, tag.tag_type.serialize(), &tag_author, &now, sauce])?;
+    }
+    if found_on_sauce {
+        let mut update_sauce_stmt = transaction.prepare(sqlite_statements::UPDATE_SAUCE_OF_IMAGE_TAG)?;
+        for &tag in tags {
+            update_sauce_stmt.execute(params![&tag_author, sauce, image.get_filename(), tag.tag_name, tag.tag_type.serialize()])?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn copy_tags_to_image(transaction: &Transaction, from_image: &ImageHandle, to_image: &ImageHandle) -> Result<()> {
+    let mut copy_tags_stmt = transaction.prepare(sqlite_statements::INSERT_TAGS_FROM_IMAGE)?;
+    copy_tags_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn add_image_alias(transaction: &Transaction, alias: &ImageHandle, image: &ImageHandle) -> Result<()> {
+    let mut add_alias_stmt = transaction.prepare(sqlite_statements::INSERT_IMAGE_ALIAS)?;
+    let now = Utc::now().naive_utc().format(DATE_TIME_FORMAT).to_string();
+    add_alias_stmt.execute(params![alias.get_filename(), image.get_filename(), &now])?;
+    Ok(())
+}
+
+pub(crate) fn add_sauce_lookup(transaction: &Transaction, image: &ImageHandle, sauce_matches: &Vec<&SauceMatch>, judge_policy: Option<&SauceJudgePolicy>) -> Result<()> {
+    let now = Utc::now().naive_utc().format(DATE_TIME_FORMAT).to_string();
+    let judge_policy = judge_policy.map(serde_json::to_string).transpose()
+        .map_err(Error::InvalidJudgePolicy)?;
+    let mut add_lookup_stmt = transaction.prepare(sqlite_statements::INSERT_SAUCE_LOOKUP)?;
+    add_lookup_stmt.execute(params![image.get_filename(), judge_policy, &now])?;
+    let mut add_candidate_stmt = transaction.prepare(sqlite_statements::INSERT_SAUCE_CANDIDATE)?;
+    for sauce_match in sauce_matches {
+        add_candidate_stmt.execute(params![image.get_filename(), sauce_match.link, sauce_match.provider, sauce_match.similarity,
+            sauce_match.resolution.0, sauce_match.resolution.1, &now])?;
+    }
+    Ok(())
+}
+
+pub(crate) fn add_dead_sauce(transaction: &Transaction, image: &ImageHandle, link: &str) -> Result<()> {
+    let now = Utc::now().naive_utc().format(DATE_TIME_FORMAT).to_string();
+    let mut add_dead_sauce_stmt = transaction.prepare(sqlite_statements::INSERT_DEAD_SAUCE)?;
+    add_dead_sauce_stmt.execute(params![image.get_filename(), link, &now])?;
+    Ok(())
+}
+
+pub(crate) fn add_image_sources(transaction: &Transaction, image: &ImageHandle, sources: &[ImageSource]) -> Result<()> {
+    let now = Utc::now().naive_utc().format(DATE_TIME_FORMAT).to_string();
+    let mut add_image_source_stmt = transaction.prepare(sqlite_statements::INSERT_IMAGE_SOURCE)?;
+    for source in sources {
+        add_image_source_stmt.execute(params![image.get_filename(), source.url, source.kind.serialize(), &now])?;
     }
     Ok(())
 }
@@ -61,6 +113,75 @@ pub(crate) fn update_image_source(transaction: &Transaction, image: &ImageHandle
     Ok(())
 }
 
+pub(crate) fn move_image_aliases(transaction: &Transaction, from_image: &ImageHandle, to_image: &ImageHandle) -> Result<()> {
+    let mut update_aliases_stmt = transaction.prepare(sqlite_statements::UPDATE_IMAGE_ALIASES)?;
+    update_aliases_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn move_sauce_lookups(transaction: &Transaction, from_image: &ImageHandle, to_image: &ImageHandle) -> Result<()> {
+    let mut update_candidates_stmt = transaction.prepare(sqlite_statements::UPDATE_SAUCE_CANDIDATES)?;
+    update_candidates_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    let mut update_lookups_stmt = transaction.prepare(sqlite_statements::UPDATE_SAUCE_LOOKUPS)?;
+    update_lookups_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn move_dead_sauces(transaction: &Transaction, from_image: &ImageHandle, to_image: &ImageHandle) -> Result<()> {
+    let mut update_dead_sauces_stmt = transaction.prepare(sqlite_statements::UPDATE_DEAD_SAUCES)?;
+    update_dead_sauces_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn move_image_sources(transaction: &Transaction, from_image: &ImageHandle, to_image: &ImageHandle) -> Result<()> {
+    let mut update_image_sources_stmt = transaction.prepare(sqlite_statements::UPDATE_IMAGE_SOURCES_FILENAME)?;
+    update_image_sources_stmt.execute(params![to_image.get_filename(), from_image.get_filename()])?;
+    Ok(())
+}
+
+// Renames all tags to their normalized name and merges them into existing tags of that name.
+// An image that already has a tag of another type with the normalized name keeps the tag under its old name,
+// as both can't be stored for one image. Returns the number of renamed tags and those collisions
+pub(crate) fn normalize_tags(transaction: &Transaction, normalizer: &TagNormalizer) -> Result<(usize, Vec<TagNameCollision>)> {
+    let tags = get_all_tags(transaction)?;
+    let mut add_tag_list_stmt = transaction.prepare(sqlite_statements::INSERT_TAG_INTO_TAG_LIST)?;
+    let mut rename_tag_stmt = transaction.prepare(sqlite_statements::UPDATE_TAG_NAME_OF_IMAGES)?;
+    let mut remove_merged_tag_stmt = transaction.prepare(sqlite_statements::DELETE_MERGED_TAG_FROM_IMAGES)?;
+    let mut remove_tag_list_stmt = transaction.prepare(sqlite_statements::DELETE_TAG_FROM_TAG_LIST)?;
+    let mut renamed = 0;
+    let mut collisions = Vec::new();
+    for tag in tags {
+        let normalized_name = normalizer.normalize(&tag.tag_name);
+        if normalized_name == tag.tag_name || normalized_name.is_empty() {
+            continue;
+        }
+        let tag_type = tag.tag_type.serialize();
+        add_tag_list_stmt.execute([&normalized_name, &tag_type])?;
+        rename_tag_stmt.execute([&normalized_name, &tag.tag_name, &tag_type])?;
+        remove_merged_tag_stmt.execute([&tag.tag_name, &normalized_name, &tag_type])?;
+
+        let colliding_images = get_images_with_tag(transaction, &tag.tag_name, &tag_type)?;
+        if colliding_images.is_empty() {
+            remove_tag_list_stmt.execute([&tag.tag_name, &tag_type])?;
+            renamed += 1;
+        } else if get_images_with_tag(transaction, &normalized_name, &tag_type)?.is_empty() {
+            remove_tag_list_stmt.execute([&normalized_name, &tag_type])?;
+        }
+        collisions.extend(colliding_images.into_iter().map(|filename| TagNameCollision {
+            filename,
+            tag: tag.clone(),
+            normalized_name: normalized_name.clone(),
+        }));
+    }
+    Ok((renamed, collisions))
+}
+
+fn get_images_with_tag(connection: &Connection, tag_name: &str, tag_type: &str) -> Result<Vec<String>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_IMAGES_WITH_TAG)?;
+    let rows = stmt.query([tag_name, tag_type])?;
+    rows.and_then(|row| -> Result<String> { Ok(row.get(0)?) }).collect()
+}
+
 // DELETE
 pub(crate) fn remove_unused_tags(transaction: &Transaction) -> Result<()> {
     transaction.execute(sqlite_statements::DELETE_UNUSED_TAGS, [])?;
@@ -81,12 +202,50 @@ pub(crate) fn remove_tags_from_images(transaction: &Transaction, image: &ImageHa
     Ok(())
 }
 
+pub(crate) fn remove_sauce_tags_from_image(transaction: &Transaction, image: &ImageHandle, sauce: &str) -> Result<()> {
+    let mut remove_tags_stmt = transaction.prepare(sqlite_statements::DELETE_SAUCE_TAGS_FROM_IMAGE)?;
+    remove_tags_stmt.execute(params![image.get_filename(), sauce])?;
+    Ok(())
+}
+
 pub(crate) fn remove_all_tags_from_image(transaction: &Transaction, image: &ImageHandle) -> Result<()> {
     let mut remove_tags_stmt = transaction.prepare(sqlite_statements::DELETE_ALL_TAGS_FROM_IMAGE)?;
     remove_tags_stmt.execute([image.get_filename()])?;
     Ok(())
 }
 
+pub(crate) fn remove_aliases_of_image(transaction: &Transaction, image: &ImageHandle) -> Result<()> {
+    let mut remove_aliases_stmt = transaction.prepare(sqlite_statements::DELETE_ALIASES_OF_IMAGE)?;
+    remove_aliases_stmt.execute([image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn remove_sauce_lookups_of_image(transaction: &Transaction, image: &ImageHandle) -> Result<()> {
+    let mut remove_candidates_stmt = transaction.prepare(sqlite_statements::DELETE_SAUCE_CANDIDATES_OF_IMAGE)?;
+    remove_candidates_stmt.execute([image.get_filename()])?;
+    let mut remove_lookups_stmt = transaction.prepare(sqlite_statements::DELETE_SAUCE_LOOKUPS_OF_IMAGE)?;
+    remove_lookups_stmt.execute([image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn remove_dead_sauce(transaction: &Transaction, image: &ImageHandle, link: &str) -> Result<()> {
+    let mut remove_dead_sauce_stmt = transaction.prepare(sqlite_statements::DELETE_DEAD_SAUCE)?;
+    remove_dead_sauce_stmt.execute(params![image.get_filename(), link])?;
+    Ok(())
+}
+
+pub(crate) fn remove_dead_sauces_of_image(transaction: &Transaction, image: &ImageHandle) -> Result<()> {
+    let mut remove_dead_sauces_stmt = transaction.prepare(sqlite_statements::DELETE_DEAD_SAUCES_OF_IMAGE)?;
+    remove_dead_sauces_stmt.execute([image.get_filename()])?;
+    Ok(())
+}
+
+pub(crate) fn remove_image_sources_of_image(transaction: &Transaction, image: &ImageHandle) -> Result<()> {
+    let mut remove_image_sources_stmt = transaction.prepare(sqlite_statements::DELETE_IMAGE_SOURCES_OF_IMAGE)?;
+    remove_image_sources_stmt.execute([image.get_filename()])?;
+    Ok(())
+}
+
 pub(crate) fn clear_all_image_tags(transaction: &Transaction) -> Result<()> {
     transaction.execute(sqlite_statements::CLEAR_IMAGE_TAGS, [])?;
     Ok(())
@@ -102,12 +261,102 @@ pub(crate) fn clear_all_tags(transaction: &Transaction) -> Result<()> {
     Ok(())
 }
 
+pub(crate) fn clear_all_image_aliases(transaction: &Transaction) -> Result<()> {
+    transaction.execute(sqlite_statements::CLEAR_IMAGE_ALIASES, [])?;
+    Ok(())
+}
+
+pub(crate) fn clear_all_sauce_lookups(transaction: &Transaction) -> Result<()> {
+    transaction.execute(sqlite_statements::CLEAR_SAUCE_CANDIDATES, [])?;
+    transaction.execute(sqlite_statements::CLEAR_SAUCE_LOOKUPS, [])?;
+    Ok(())
+}
+
+pub(crate) fn clear_all_dead_sauces(transaction: &Transaction) -> Result<()> {
+    transaction.execute(sqlite_statements::CLEAR_DEAD_SAUCES, [])?;
+    Ok(())
+}
+
+pub(crate) fn clear_all_image_sources(transaction: &Transaction) -> Result<()> {
+    transaction.execute(sqlite_statements::CLEAR_IMAGE_SOURCES, [])?;
+    Ok(())
+}
+
 // SELECT
 pub(crate) fn get_image(connection: &Connection, image: &ImageHandle) -> Result<Option<ImageInfo>> {
     let mut stmt = connection.prepare(sqlite_statements::SELECT_IMAGE)?;
     let rows = stmt.query([image.get_filename()])?;
-    query_helpers::query_row_as_image(rows)
+    query_helpers::query_row_as_image(rows)?
+        .map(|image| with_image_sources(connection, image))
+        .transpose()
 }
+
+pub(crate) fn get_image_by_alias(connection: &Connection, alias: &ImageHandle) -> Result<Option<ImageInfo>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_IMAGE_BY_ALIAS)?;
+    let rows = stmt.query([alias.get_filename()])?;
+    query_helpers::query_row_as_image(rows)?
+        .map(|image| with_image_sources(connection, image))
+        .transpose()
+}
+
+pub(crate) fn get_image_sources(connection: &Connection, image: &ImageHandle) -> Result<Vec<ImageSource>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_IMAGE_SOURCES)?;
+    let rows = stmt.query([image.get_filename()])?;
+    query_helpers::query_rows_as_image_sources(rows)
+}
+
+fn with_image_sources(connection: &Connection, image: ImageInfo) -> Result<ImageInfo> {
+    let sources = get_image_sources(connection, image.get_image())?;
+    Ok(image.with_sources(sources))
+}
+
+// loads the sources of all images at once instead of querying them image by image
+fn with_all_image_sources(connection: &Connection, images: Vec<ImageInfo>) -> Result<Vec<ImageInfo>> {
+    if images.is_empty() {
+        return Ok(images);
+    }
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_ALL_IMAGE_SOURCES)?;
+    let mut sources: HashMap<String, Vec<ImageSource>> = HashMap::new();
+    for (filename, source) in query_helpers::query_rows_as_image_sources_of_images(stmt.query([])?)? {
+        sources.entry(filename).or_default().push(source);
+    }
+    Ok(images.into_iter()
+        .map(|image| {
+            let image_sources = sources.remove(image.get_image().get_filename()).unwrap_or_default();
+            image.with_sources(image_sources)
+        })
+        .collect())
+}
+
+pub(crate) fn get_sauce_candidates(connection: &Connection, image: &ImageHandle) -> Result<Vec<SauceCandidate>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_SAUCE_CANDIDATES)?;
+    let rows = stmt.query([image.get_filename()])?;
+    query_helpers::query_rows_as_sauce_candidates(rows)
+}
+
+pub(crate) fn get_sauce_lookups(connection: &Connection, image: &ImageHandle) -> Result<Vec<SauceLookup>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_SAUCE_LOOKUPS)?;
+    let rows = stmt.query([image.get_filename()])?;
+    query_helpers::query_rows_as_sauce_lookups(rows)
+}
+
+pub(crate) fn get_dead_sauces(connection: &Connection, image: &ImageHandle) -> Result<Vec<String>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_DEAD_SAUCES)?;
+    let rows = stmt.query([image.get_filename()])?;
+    rows.and_then(|row| -> Result<String> { Ok(row.get(0)?) }).collect()
+}
+
+// number of recorded lookups and the date of the latest one
+pub(crate) fn get_sauce_lookup_summary(connection: &Connection, image: &ImageHandle) -> Result<(u32, Option<NaiveDateTime>)> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_SAUCE_LOOKUP_SUMMARY)?;
+    let (count, latest) = stmt.query_row([image.get_filename()], |row| Ok((row.get::<usize, u32>(0)?, row.get::<usize, Option<String>>(1)?)))?;
+    let latest = latest
+        .map(|date| NaiveDateTime::parse_from_str(&date, DATE_TIME_FORMAT))
+        .transpose()
+        .map_err(Error::InvalidDateFormat)?;
+    Ok((count, latest))
+}
+
 /*
 pub fn get_all_files(connection: &Connection) -> Result<Vec<ImageHandle>> {
     let mut stmt = connection.prepare(sqlite_statements::SELECT_ALL_FILES)?;
@@ -133,6 +382,7 @@ pub(crate) fn get_images(connection: &Connection, included_tags: &Vec<PantsuTag>
         };
     let formatted_stmt = formatted_stmt.replace(sqlite_statements::SAUCE_TYPE_PLACEHOLDER, match sauce_type {
         SauceType::Existing => sauce::EXISTING_FLAG,
+        SauceType::Unsure => sauce::UNSURE_FLAG,
         SauceType::NotExisting => sauce::NOT_EXISTING_FLAG,
         SauceType::NotChecked => sauce::NOT_CHECKED_FLAG,
         SauceType::Any => "%",
@@ -143,7 +393,7 @@ pub(crate) fn get_images(connection: &Connection, included_tags: &Vec<PantsuTag>
 
     let included_tags: Vec<String> = included_tags.iter().map(|t| t.serialize()).collect();
     let excluded_tags: Vec<String> = excluded_tags.iter().map(|t| t.serialize()).collect();
-    if included_tags.len()!=0 && excluded_tags.len()!=0 {
+    let images = if included_tags.len()!=0 && excluded_tags.len()!=0 {
         let mut vec = Vec::<String>::new();
         vec.extend(included_tags);
         vec.extend(excluded_tags);
@@ -157,7 +407,8 @@ pub(crate) fn get_images(connection: &Connection, included_tags: &Vec<PantsuTag>
         query_helpers::query_rows_as_images(stmt.query(params)?)
     } else {
         query_helpers::query_rows_as_images(stmt.query([])?)
-    }
+    }?;
+    with_all_image_sources(connection, images)
 }
 
 pub(crate) fn get_tags_for_image(connection: &Connection, image: &ImageHandle, sort_order: &SortOrder<TagSortOption>) -> Result<Vec<PantsuTagInfo>> {
@@ -183,6 +434,36 @@ pub(crate) fn get_tags_with_types(connection: &Connection, types: &Vec<PantsuTag
     query_helpers::query_rows_as_tags(rows)
 }
 
+pub(crate) fn get_tags_matching_glob(connection: &Connection, pattern: &str) -> Result<Vec<PantsuTag>> {
+    let mut stmt = connection.prepare(sqlite_statements::SELECT_TAGS_MATCHING_GLOB)?;
+    let rows = stmt.query(params![pattern])?;
+    query_helpers::query_rows_as_tags(rows)
+}
+
+pub(crate) fn get_tag_counts(connection: &Connection, types: &[PantsuTagType]) -> Result<Vec<PantsuTagCount>> {
+    let formatted_stmt = match types.is_empty() {
+        true => sqlite_statements::SELECT_TAG_COUNTS.to_string(),
+        false => sqlite_statements::SELECT_TAG_COUNTS_WITH_TYPE
+            .replace(sqlite_statements::SELECT_TAGS_WITH_TYPE_PLACEHOLDER, &query_helpers::repeat_vars(types.len())),
+    };
+    let mut stmt = connection.prepare(&formatted_stmt)?;
+    let params = rusqlite::params_from_iter(types.iter().map(|t| t.serialize()));
+    let rows = stmt.query(params)?;
+    query_helpers::query_rows_as_tag_counts(rows)
+}
+
+pub(crate) fn get_related_tags(connection: &Connection, tags: &[&PantsuTag]) -> Result<Vec<RelatedPantsuTag>> {
+    let formatted_stmt = sqlite_statements::SELECT_RELATED_TAGS
+        .replace(sqlite_statements::SELECT_IMAGES_FOR_INCLUDING_TAGS_PLACEHOLDER, &query_helpers::repeat_vars(tags.len()))
+        .replace(sqlite_statements::SELECT_IMAGES_FOR_TAGS_TAG_COUNT, &tags.len().to_string());
+    let mut stmt = connection.prepare(&formatted_stmt)?;
+    // the tag list is used twice, for the images of the query and to leave out the tags of the query
+    let serialized_tags = tags.iter().map(|t| t.serialize()).collect::<Vec<String>>();
+    let params = rusqlite::params_from_iter(serialized_tags.iter().chain(serialized_tags.iter()));
+    let rows = stmt.query(params)?;
+    query_helpers::query_rows_as_related_tags(rows)
+}
+
 pub(crate) fn get_tags_for_image_with_types(connection: &Connection, image: &ImageHandle, types: &Vec<PantsuTagType>, sort_order: &SortOrder<TagSortOption>) -> Result<Vec<PantsuTagInfo>> {
     let formatted_stmt = sqlite_statements::SELECT_TAGS_FOR_IMAGE_WITH_TYPE
         .replace(sqlite_statements::SELECT_TAGS_WITH_TYPE_PLACEHOLDER, &query_helpers::repeat_vars(types.len()))
@@ -202,9 +483,11 @@ mod query_helpers {
     use rusqlite::{Row, Rows};
     use crate::common::error::Result;
     use crate::common::image_info::{ImageInfo, DATE_TIME_FORMAT};
-    use crate::sauce::{EXISTING_FLAG, NOT_EXISTING_FLAG, NOT_CHECKED_FLAG};
-    use crate::common::pantsu_tag::{PantsuTag, PantsuTagInfo, PantsuTagAuthor};
-    use crate::{Error, Sauce, PantsuTagType, sauce, ImageHandle};
+    use crate::common::image_source::{ImageSource, ImageSourceKind};
+    use crate::common::rating::Rating;
+    use crate::sauce::{EXISTING_FLAG, UNSURE_FLAG, NOT_EXISTING_FLAG, NOT_CHECKED_FLAG};
+    use crate::common::pantsu_tag::{PantsuTag, PantsuTagCount, PantsuTagInfo, PantsuTagAuthor, RelatedPantsuTag};
+    use crate::{Error, Sauce, SauceCandidate, SauceLookup, PantsuTagType, sauce, ImageHandle};
 
     pub fn query_row_as_image(rows: Rows) -> Result<Option<ImageInfo>> {
         let rows = query_rows_as_images(rows)?;
@@ -218,12 +501,25 @@ mod query_helpers {
         rows.and_then(image_info_from_row).collect::<Result<Vec<ImageInfo>>>()
     }
 
+    pub fn query_rows_as_image_sources(rows: Rows) -> Result<Vec<ImageSource>> {
+        rows.and_then(|row| -> Result<ImageSource> {
+            Ok(ImageSource::new(row.get(0)?, ImageSourceKind::deserialize(&row.get::<usize, String>(1)?)?))
+        }).collect()
+    }
+
+    pub fn query_rows_as_image_sources_of_images(rows: Rows) -> Result<Vec<(String, ImageSource)>> {
+        rows.and_then(|row| -> Result<(String, ImageSource)> {
+            Ok((row.get(0)?, ImageSource::new(row.get(1)?, ImageSourceKind::deserialize(&row.get::<usize, String>(2)?)?)))
+        }).collect()
+    }
+
     fn image_info_from_row(row: &Row) -> Result<ImageInfo> {
         Ok(
             ImageInfo::new(
                 ImageHandle::new(row.get(0)?)?,
                 match row.get::<usize, String>(1)?.as_str() {
                     EXISTING_FLAG => Sauce::Match(sauce::url_from_str(&row.get::<usize, String>(2)?)?),
+                    UNSURE_FLAG => Sauce::Unsure,
                     NOT_EXISTING_FLAG => Sauce::NotExisting,
                     NOT_CHECKED_FLAG => Sauce::NotChecked,
                     s => return Err(Error::InvalidSauceType(s.to_string()))
@@ -233,10 +529,17 @@ mod query_helpers {
                     .or_else(|e| Err(Error::InvalidDateFormat(e)))?,
                 NaiveDateTime::parse_from_str(row.get::<usize, String>(6)?.as_str(), DATE_TIME_FORMAT)
                     .or_else(|e| Err(Error::InvalidDateFormat(e)))?
-            )
+            ).with_rating(rating_from_tag_names(row.get(7)?))
         )
     }
 
+    // the rating tags of an image joined by commas, the most explicit known rating wins
+    fn rating_from_tag_names(tag_names: Option<String>) -> Option<Rating> {
+        tag_names?.split(',')
+            .filter_map(|name| Rating::deserialize(name).ok())
+            .max()
+    }
+
     pub fn query_rows_as_tags(rows: Rows) -> Result<Vec<PantsuTag>> {
         let rows: Vec<PantsuTag> = rows
             .mapped(|row| -> rusqlite::Result<(String, String)> {
@@ -252,18 +555,46 @@ mod query_helpers {
         Ok(rows)
     }
 
+    pub fn query_rows_as_tag_counts(rows: Rows) -> Result<Vec<PantsuTagCount>> {
+        rows.mapped(|row| -> rusqlite::Result<(String, String, usize)> {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map(|r| {
+                let (tag_name, tag_type, count) = r?;
+                Ok(PantsuTagCount { tag: PantsuTag { tag_type: PantsuTagType::deserialize(&tag_type)?, tag_name }, count })
+            })
+            .collect::<Result<Vec<PantsuTagCount>>>()
+    }
+
+    pub fn query_rows_as_related_tags(rows: Rows) -> Result<Vec<RelatedPantsuTag>> {
+        rows.mapped(|row| -> rusqlite::Result<(String, String, usize, usize, usize)> {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            })
+            .map(|r| {
+                let (tag_name, tag_type, cooccurrences, count, query_count) = r?;
+                Ok(RelatedPantsuTag {
+                    tag: PantsuTag { tag_type: PantsuTagType::deserialize(&tag_type)?, tag_name },
+                    cooccurrences,
+                    count,
+                    frequency: cooccurrences as f32 / query_count.max(1) as f32,
+                })
+            })
+            .collect::<Result<Vec<RelatedPantsuTag>>>()
+    }
+
     pub fn query_rows_as_tag_infos(rows: Rows) -> Result<Vec<PantsuTagInfo>> {
         let rows: Vec<PantsuTagInfo> = rows
-            .mapped(|row| -> rusqlite::Result<(String, String, String, String)> {
-                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            .mapped(|row| -> rusqlite::Result<(String, String, String, String, Option<String>)> {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
             })
             .map(|r| {
                 match r {
-                    Ok((tag_name, tag_type, tag_author, date_added)) => Ok(PantsuTagInfo {
+                    Ok((tag_name, tag_type, tag_author, date_added, sauce)) => Ok(PantsuTagInfo {
                         tag: PantsuTag { tag_type: PantsuTagType::deserialize(&tag_type)? , tag_name: tag_name },
                         tag_author: PantsuTagAuthor::deserialize(&tag_author)?,
                         date_added: NaiveDateTime::parse_from_str(&date_added, DATE_TIME_FORMAT)
-                            .or_else(|e| Err(Error::InvalidDateFormat(e)))?
+                            .or_else(|e| Err(Error::InvalidDateFormat(e)))?,
+                        sauce,
                     }),
                     Err(e) => Err(Error::SQLError(e))
                 }
@@ -272,6 +603,33 @@ mod query_helpers {
         Ok(rows)
     }
 
+    pub fn query_rows_as_sauce_candidates(rows: Rows) -> Result<Vec<SauceCandidate>> {
+        rows.and_then(|row| -> Result<SauceCandidate> {
+            Ok(SauceCandidate {
+                link: row.get(0)?,
+                provider: row.get(1)?,
+                similarity: row.get(2)?,
+                resolution: (row.get(3)?, row.get(4)?),
+                date_added: NaiveDateTime::parse_from_str(row.get::<usize, String>(5)?.as_str(), DATE_TIME_FORMAT)
+                    .map_err(Error::InvalidDateFormat)?,
+            })
+        }).collect()
+    }
+
+    pub fn query_rows_as_sauce_lookups(rows: Rows) -> Result<Vec<SauceLookup>> {
+        rows.and_then(|row| -> Result<SauceLookup> {
+            let judge_policy = row.get::<usize, Option<String>>(0)?
+                .map(|policy| serde_json::from_str(&policy))
+                .transpose()
+                .map_err(Error::InvalidJudgePolicy)?;
+            Ok(SauceLookup {
+                judge_policy,
+                date_added: NaiveDateTime::parse_from_str(row.get::<usize, String>(1)?.as_str(), DATE_TIME_FORMAT)
+                    .map_err(Error::InvalidDateFormat)?,
+            })
+        }).collect()
+    }
+
     pub fn repeat_vars(count: usize) -> String {
         assert_ne!(count, 0);
         let mut s = "?,".repeat(count);
@@ -3,7 +3,7 @@ use log::info;
 use crate::Sauce;
 use crate::Error;
 use crate::common::image_info::ImageInfo;
-use crate::common::pantsu_tag::PantsuTagInfo;
+use crate::common::pantsu_tag::{PantsuTagAuthor, PantsuTagInfo};
 use std::io::Write;
 use std::path::Path;
 use crate::common::error::Result;
@@ -41,19 +41,33 @@ pub(crate) fn import_tags(pdb: &mut PantsuDB, file: &Path) -> Result<()> {
             Some(image) => image,
             None => continue
         };
+        let local_sauce = local_image.get_sauce();
+        // booru tags of older exports have no sauce, they were found on the sauce of the image
+        let image_sauce = match (local_sauce, image_info.get_sauce()) {
+            (Sauce::Match(url), _) | (_, Sauce::Match(url)) => Some(url.to_string()),
+            _ => None,
+        };
         for tag in tags {
-            pdb.update_images_transaction()
-            .for_image(image_info.get_image())
-            .tag_author(&tag.tag_author)
-            .add_tag(&tag.tag)
-            .execute()?;
+            let transaction = pdb.update_images_transaction()
+                .for_image(image_info.get_image())
+                .tag_author(&tag.tag_author)
+                .add_tag(&tag.tag);
+            let tag_sauce = match tag.tag_author {
+                PantsuTagAuthor::User => None,
+                _ => tag.sauce.as_deref().or(image_sauce.as_deref()),
+            };
+            match tag_sauce {
+                Some(sauce) => transaction.tag_sauce(sauce).execute(),
+                None => transaction.execute(),
+            }?;
         }
-        let local_sauce = local_image.get_sauce();
         let transaction = pdb.update_images_transaction()
-            .for_image(image_info.get_image());
+            .for_image(image_info.get_image())
+            .add_sources(image_info.get_sources());
         match (local_sauce, image_info.get_sauce()) {
             (Sauce::Match(_), _) => transaction.execute(),
             (_, Sauce::NotChecked) => transaction.execute(),
+            (_, Sauce::Unsure) => transaction.execute(), // sauce candidates are not exported, nothing to review
             (_, _) => transaction.update_sauce(image_info.get_sauce()).execute()
         }?;
         info!("Updated image '{}' from import file", image_info.get_image().get_filename())
@@ -63,6 +77,7 @@ pub(crate) fn import_tags(pdb: &mut PantsuDB, file: &Path) -> Result<()> {
 
 pub(crate) fn export_tags(pdb: &PantsuDB, path: &Path) -> Result<()> {
     let images = pdb.get_images_transaction()
+        .max_rating(None)
         .execute()?;
     let lines = images.into_iter()
         .map(|i| serialize_image(&pdb, &i))
@@ -1,12 +1,20 @@
 use std::path::Path;
 use rusqlite::{Connection, OpenFlags};
 use crate::common::error::{Error};
-use log::{debug};
+use crate::common::pantsu_tag::TagNormalizer;
+use log::{debug, warn};
 use crate::db::{db_calls, sqlite_statements};
 
 pub fn open(db_path: &Path) -> Result<Connection, Error> {
     let pantsu_db_updates: Vec<&dyn Fn(&mut Connection) -> Result<(), Error>> = vec![
-        //eg: &db_update_1_2,
+        &db_update_1_2,
+        &db_update_2_3,
+        &db_update_3_4,
+        &db_update_4_5,
+        &db_update_5_6,
+        &db_update_6_7,
+        &db_update_7_8,
+        &db_update_8_9,
     ];
     let pantsu_db_newest_version = pantsu_db_updates.len() + 1;
 
@@ -42,4 +50,54 @@ fn db_init_new(connection: &mut Connection) -> Result<(), Error> {
     debug!("Initializing database");
     connection.execute_batch(sqlite_statements::DB_INIT_TABLES)?;
     Ok(())
-}
\ No newline at end of file
+}
+fn db_update_1_2(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 1 to 2");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_1_2)?;
+    Ok(())
+}
+fn db_update_2_3(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 2 to 3");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_2_3)?;
+    Ok(())
+}
+fn db_update_3_4(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 3 to 4");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_3_4)?;
+    Ok(())
+}
+fn db_update_4_5(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 4 to 5");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_4_5)?;
+    Ok(())
+}
+fn db_update_5_6(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 5 to 6");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_5_6)?;
+    Ok(())
+}
+fn db_update_6_7(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 6 to 7");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_6_7)?;
+    Ok(())
+}
+
+fn db_update_7_8(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 7 to 8");
+    let transaction = connection.transaction()?;
+    // the configured normalizer may differ, tags are renamed with it by the normalize-tags command
+    let (renamed, collisions) = db_calls::normalize_tags(&transaction, &TagNormalizer::default())?;
+    transaction.commit()?;
+    debug!("Normalized {} tag names", renamed);
+    for collision in collisions {
+        warn!("Kept tag '{}' of image '{}', it already has a tag of another type named '{}'",
+            collision.tag, collision.filename, collision.normalized_name);
+    }
+    Ok(())
+}
+
+fn db_update_8_9(connection: &mut Connection) -> Result<(), Error> {
+    debug!("Updating database from version 8 to 9");
+    connection.execute_batch(sqlite_statements::DB_UPDATE_8_9)?;
+    Ok(())
+}
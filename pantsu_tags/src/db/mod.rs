@@ -2,8 +2,9 @@ use std::path::{Path, PathBuf};
 use rusqlite::{Connection};
 
 use crate::common::error::Result;
-use crate::{common, Error, ImageHandle};
-use crate::db::transactions::{DeleteImagesTransaction, InsertImagesTransaction, SelectImagesTransaction, SelectImageTransaction, SelectTagsTransaction, SelectImageTagsTransaction, UpdateImagesTransaction};
+use crate::common::pantsu_tag::{TagNameCollision, TagNormalizer};
+use crate::{common, Error, ImageHandle, Rating};
+use crate::db::transactions::{DeleteImagesTransaction, InsertImagesTransaction, InsertSauceCandidatesTransaction, MergeImagesTransaction, SelectDeadSaucesTransaction, SelectImagesTransaction, SelectImageTransaction, SelectSauceCandidatesTransaction, SelectSauceLookupsTransaction, SelectTagsTransaction, SelectSimilarTagsTransaction, SelectTagCountsTransaction, SelectRelatedTagsTransaction, SelectImageTagsTransaction, UpdateDeadSaucesTransaction, UpdateImagesTransaction};
 
 mod db_calls;
 mod sqlite_statements;
@@ -22,12 +23,14 @@ pub enum AspectRatio {
 pub(crate) enum SauceType {
     NotChecked,
     NotExisting,
+    Unsure,
     Existing,
     Any,
 }
 
 pub struct PantsuDB {
-    conn: Connection
+    conn: Connection,
+    max_rating: Option<Rating>,
 }
 
 impl PantsuDB {
@@ -47,7 +50,14 @@ impl PantsuDB {
             Err(Error::DirectoryCreateError(e, common::get_path(path_buf.as_path())))
         )?;
         let conn = db_init::open(path_buf.as_path())?;
-        Ok(PantsuDB { conn })
+        Ok(PantsuDB { conn, max_rating: None })
+    }
+
+    // the highest rating get_images_transaction selects by default, e.g. at work.
+    // Selections can override it with SelectImagesTransaction::max_rating
+    pub fn with_max_rating(mut self, max_rating: Option<Rating>) -> Self {
+        self.max_rating = max_rating;
+        self
     }
 
     pub fn get_db_version(&self) -> Result<usize> {
@@ -59,6 +69,10 @@ impl PantsuDB {
         let transaction = self.conn.transaction()?;
 
         db_calls::clear_all_image_tags(&transaction)?;
+        db_calls::clear_all_image_aliases(&transaction)?;
+        db_calls::clear_all_sauce_lookups(&transaction)?;
+        db_calls::clear_all_dead_sauces(&transaction)?;
+        db_calls::clear_all_image_sources(&transaction)?;
         db_calls::clear_all_images(&transaction)?;
         db_calls::clear_all_tags(&transaction)?;
 
@@ -72,29 +86,66 @@ impl PantsuDB {
     }
 
     pub fn get_images_transaction<'a>(&'a self) -> SelectImagesTransaction<'a> {
-        SelectImagesTransaction::new(&self.conn)
+        SelectImagesTransaction::new(&self.conn, self.max_rating)
     }
 
     pub fn get_tags_transaction<'a>(&'a self) -> SelectTagsTransaction<'a> {
         SelectTagsTransaction::new(&self.conn)
     }
 
+    // tags with a name close to the text, e.g. to correct typos
+    pub fn get_similar_tags_transaction<'a>(&'a self, text: &'a str) -> SelectSimilarTagsTransaction<'a> {
+        SelectSimilarTagsTransaction::new(&self.conn, text)
+    }
+
+    pub fn get_tag_counts_transaction<'a>(&'a self) -> SelectTagCountsTransaction<'a> {
+        SelectTagCountsTransaction::new(&self.conn)
+    }
+
+    pub fn get_related_tags_transaction<'a>(&'a self) -> SelectRelatedTagsTransaction<'a> {
+        SelectRelatedTagsTransaction::new(&self.conn)
+    }
+
     pub fn get_image_tags_transaction<'a>(&'a self, image: &'a ImageHandle) -> SelectImageTagsTransaction<'a> {
         SelectImageTagsTransaction::new(&self.conn, image)
     }
 
+    pub fn get_sauce_candidates_transaction<'a>(&'a self, image: &'a ImageHandle) -> SelectSauceCandidatesTransaction<'a> {
+        SelectSauceCandidatesTransaction::new(&self.conn, image)
+    }
+
+    pub fn get_sauce_lookups_transaction<'a>(&'a self, image: &'a ImageHandle) -> SelectSauceLookupsTransaction<'a> {
+        SelectSauceLookupsTransaction::new(&self.conn, image)
+    }
+
+    pub fn get_dead_sauces_transaction<'a>(&'a self, image: &'a ImageHandle) -> SelectDeadSaucesTransaction<'a> {
+        SelectDeadSaucesTransaction::new(&self.conn, image)
+    }
+
     pub fn update_images_transaction<'a>(&'a mut self) -> UpdateImagesTransaction<'a> {
         UpdateImagesTransaction::new(&mut self.conn)
     }
 
+    pub fn update_dead_sauces_transaction<'a>(&'a mut self, image: &'a ImageHandle) -> UpdateDeadSaucesTransaction<'a> {
+        UpdateDeadSaucesTransaction::new(&mut self.conn, image)
+    }
+
     pub fn add_images_transaction<'a>(&'a mut self) -> InsertImagesTransaction<'a> {
         InsertImagesTransaction::new(&mut self.conn)
     }
 
+    pub fn add_sauce_candidates_transaction<'a>(&'a mut self, image: &'a ImageHandle) -> InsertSauceCandidatesTransaction<'a> {
+        InsertSauceCandidatesTransaction::new(&mut self.conn, image)
+    }
+
     pub fn remove_image_transaction<'a>(&'a mut self) -> DeleteImagesTransaction<'a> {
         DeleteImagesTransaction::new(&mut self.conn)
     }
 
+    pub fn merge_images<'a>(&'a mut self, keep: &'a ImageHandle, discard: &'a ImageHandle) -> MergeImagesTransaction<'a> {
+        MergeImagesTransaction::new(&mut self.conn, keep, discard)
+    }
+
     pub fn import_tags(&mut self, import_file_path: &Path) -> Result<()> {
         db_import_export::import_tags(self, import_file_path)
     }
@@ -102,6 +153,15 @@ impl PantsuDB {
     pub fn export_tags(&mut self, export_file_path: &Path) -> Result<()> {
         db_import_export::export_tags(self, export_file_path)
     }
+
+    // renames all tags with the given tag normalizer, e.g. after it was reconfigured,
+    // returns the number of renamed tags and the tags that collide with a tag of another type on an image
+    pub fn normalize_tags(&mut self, normalizer: &TagNormalizer) -> Result<(usize, Vec<TagNameCollision>)> {
+        let transaction = self.conn.transaction()?;
+        let normalized = db_calls::normalize_tags(&transaction, normalizer)?;
+        transaction.commit()?;
+        Ok(normalized)
+    }
 }
 
 #[cfg(test)]
@@ -114,7 +174,9 @@ mod tests {
     use crate::db::PantsuDB;
 
     use serial_test::serial;
-    use crate::{PantsuTag, PantsuTagType, Sauce, sauce};
+    use std::time::Duration;
+    use crate::{ImageInfo, ImageSource, ImageSourceKind, PantsuTag, PantsuTagType, Rating, Sauce, SauceJudgePolicy, SauceMatch, sauce};
+    use crate::common::pantsu_tag::{PantsuTagAuthor, PantsuTagDiff, PantsuTagInfo, TagNormalizer};
 
     #[test]
     #[serial]
@@ -545,6 +607,461 @@ mod tests {
         assert_eq!(imgi3, None);
     }
 
+    #[test]
+    #[serial]
+    fn db_merge_images() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        pdb.update_images_transaction()
+            .for_image(&img)
+            .add_tags(&vec!["general:Haha".parse().unwrap(), "artist:Hehe".parse().unwrap()])
+            .execute()
+            .unwrap();
+        pdb.update_images_transaction()
+            .for_image(&img2)
+            .tag_author(&PantsuTagAuthor::Gelbooru)
+            .add_tags(&vec!["general:Haha".parse().unwrap(), "character:Hihi".parse().unwrap()])
+            .execute()
+            .unwrap();
+
+        let sauce = pdb.merge_images(&img, &img2).keep_alias().execute().unwrap();
+        assert_eq!(sauce, Sauce::Match(sauce::url_from_str("http://real.url").unwrap()));
+
+        let tags = pdb.get_image_tags_transaction(&img).execute().unwrap();
+        assert_eq!(tags.len(), 3);
+        assert!(tags.iter().any(|t| t.tag == "general:Haha".parse().unwrap() && t.tag_author == PantsuTagAuthor::User));
+        assert!(tags.iter().any(|t| t.tag == "character:Hihi".parse().unwrap() && t.tag_author == PantsuTagAuthor::Gelbooru));
+        assert_eq!(pdb.get_images_transaction().execute().unwrap().len(), 1);
+
+        let merged = pdb.get_image_transaction(&img2).execute().unwrap().unwrap();
+        assert_eq!(merged.get_image(), &img);
+        assert_eq!(merged.get_sauce(), &sauce);
+    }
+
+    #[test]
+    #[serial]
+    fn db_merge_image_into_itself() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        pdb.update_images_transaction().for_image(&img).add_tags(&vec!["general:Haha".parse().unwrap()]).execute().unwrap();
+
+        let res = pdb.merge_images(&img, &img).keep_alias().execute();
+        assert!(matches!(res, Err(Error::MergeIntoSelf(_))));
+        assert!(pdb.get_image_transaction(&img).execute().unwrap().is_some());
+        assert_eq!(pdb.get_image_tags_transaction(&img).execute().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn db_sauce_candidates() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        let matches = vec![
+            SauceMatch { link: String::from("https://gelbooru.com/index.php?page=post&s=view&id=1"), similarity: 80, resolution: (100, 200), provider: String::from("iqdb") },
+            SauceMatch { link: String::from("https://gelbooru.com/index.php?page=post&s=view&id=2"), similarity: 95, resolution: (50, 100), provider: String::from("iqdb") },
+        ];
+        let policy = SauceJudgePolicy { found_similarity: 85, ..SauceJudgePolicy::default() };
+        assert_eq!(pdb.add_sauce_candidates_transaction(&img).add_candidates(&matches).judged_by(&policy).execute().unwrap(), 2);
+        let lookups = pdb.get_sauce_lookups_transaction(&img).execute().unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].judge_policy, Some(policy));
+
+        let candidates = pdb.get_sauce_candidates_transaction(&img).latest_lookup_only().execute().unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].link, matches[1].link);
+        assert_eq!(candidates[0].provider, "iqdb");
+        assert_eq!(candidates[0].resolution, (50, 100));
+        assert_eq!(candidates[1].to_sauce_match().link, matches[0].link);
+
+        pdb.merge_images(&img2, &img).execute().unwrap();
+        assert_eq!(pdb.get_sauce_candidates_transaction(&img2).execute().unwrap().len(), 2);
+        assert_eq!(pdb.get_sauce_lookups_transaction(&img2).execute().unwrap().len(), 1);
+        pdb.remove_image_transaction().remove_image(&img2).execute().unwrap();
+        assert!(pdb.get_sauce_candidates_transaction(&img2).execute().unwrap().is_empty());
+        assert!(pdb.get_sauce_lookups_transaction(&img2).execute().unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn db_unsure_sauce() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        pdb.update_images_transaction().for_image(&img).update_sauce(&Sauce::Unsure).execute().unwrap();
+
+        let unsure_images = pdb.get_images_transaction().with_unsure_sauce().execute().unwrap();
+        assert_eq!(unsure_images.len(), 1);
+        assert_eq!(unsure_images[0].get_image(), &img);
+        assert_eq!(unsure_images[0].get_sauce(), &Sauce::Unsure);
+        assert!(pdb.get_images_transaction().with_not_checked_sauce().execute().unwrap().iter().all(|i| i.get_image() != &img));
+    }
+
+    #[test]
+    #[serial]
+    fn db_sauce_tags_diff() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        pdb.update_images_transaction()
+            .for_image(&img)
+            .add_tags(&vec!["general:Haha".parse().unwrap()])
+            .execute()
+            .unwrap();
+        pdb.update_images_transaction()
+            .for_image(&img)
+            .tag_author(&PantsuTagAuthor::Gelbooru)
+            .add_tags(&vec!["artist:Hehe".parse().unwrap(), "character:Hihi".parse().unwrap()])
+            .execute()
+            .unwrap();
+
+        let stored_tags: Vec<PantsuTag> = pdb.get_image_tags_transaction(&img)
+            .with_author(&PantsuTagAuthor::Gelbooru)
+            .execute()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.tag)
+            .collect();
+        assert_eq!(stored_tags.len(), 2);
+        let diff = PantsuTagDiff::new(&stored_tags, &["artist:Hehe".parse().unwrap(), "general:Hoho".parse().unwrap()]);
+        assert_eq!(diff.added, vec!["general:Hoho".parse().unwrap()]);
+        assert_eq!(diff.removed, vec!["character:Hihi".parse().unwrap()]);
+
+        assert!(pdb.get_images_transaction().not_modified_for(Duration::from_secs(60 * 60)).execute().unwrap().is_empty());
+        assert_eq!(pdb.get_images_transaction().not_modified_for(Duration::ZERO).execute().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn db_recheck_not_existing_sauce() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        pdb.update_images_transaction().for_image(&img).update_sauce(&Sauce::NotExisting).execute().unwrap();
+        pdb.add_sauce_candidates_transaction(&img).execute().unwrap();
+
+        let due = |pdb: &PantsuDB, min_age| pdb.get_images_transaction().with_not_existing_sauce().recheck_due(min_age).execute().unwrap().len();
+        assert_eq!(due(&pdb, Duration::ZERO), 1);
+        assert_eq!(due(&pdb, Duration::from_secs(60 * 60)), 0);
+        assert_eq!(pdb.get_sauce_lookups_transaction(&img).execute().unwrap()[0].judge_policy, None);
+    }
+
+    #[test]
+    #[serial]
+    fn db_dead_sauces() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        pdb.update_dead_sauces_transaction(&img2).mark_dead("http://real.url/").mark_dead("http://other.url/").execute().unwrap();
+        pdb.update_dead_sauces_transaction(&img2).mark_alive("http://other.url/").execute().unwrap();
+        assert_eq!(pdb.get_dead_sauces_transaction(&img2).execute().unwrap(), vec![String::from("http://real.url/")]);
+        assert_eq!(pdb.get_images_transaction().with_dead_sauce().execute().unwrap().len(), 1);
+
+        pdb.merge_images(&img, &img2).execute().unwrap();
+        assert_eq!(pdb.get_dead_sauces_transaction(&img).execute().unwrap(), vec![String::from("http://real.url/")]);
+        pdb.remove_image_transaction().remove_image(&img).execute().unwrap();
+        assert!(pdb.get_dead_sauces_transaction(&img).execute().unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn db_image_sources() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        let original = vec![ImageSource::new(String::from("https://www.pixiv.net/artworks/12345"), ImageSourceKind::Original)];
+        pdb.update_images_transaction().for_image(&img2).add_sources(&original).execute().unwrap();
+
+        let imgi2 = pdb.get_image_transaction(&img2).execute().unwrap().unwrap();
+        assert_eq!(imgi2.get_sources(), &[
+            ImageSource::new(String::from("http://real.url/"), ImageSourceKind::BooruPost),
+            original[0].clone(),
+        ]);
+        assert_eq!(ImageInfo::deserialize(&imgi2.serialize()).unwrap(), imgi2);
+        let old_format = imgi2.serialize().rsplitn(2, ';').last().unwrap().to_string();
+        assert!(ImageInfo::deserialize(&old_format).unwrap().get_sources().is_empty());
+
+        pdb.merge_images(&img, &img2).execute().unwrap();
+        let images = pdb.get_images_transaction().execute().unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].get_sources().len(), 2);
+        pdb.remove_image_transaction().remove_image(&img).execute().unwrap();
+        assert!(pdb.get_image_transaction(&img).execute().unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn db_tag_sauces() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        let old_sauce = Sauce::Match(sauce::url_from_str("https://gelbooru.com/index.php?id=1").unwrap());
+        let new_sauce = Sauce::Match(sauce::url_from_str("https://gelbooru.com/index.php?id=2").unwrap());
+        let old_tags = vec!["general:Haha".parse().unwrap(), "artist:Hehe".parse().unwrap()];
+        let new_tags = vec!["general:Haha".parse().unwrap(), "character:Hihi".parse().unwrap()];
+        let user_tags = vec!["general:Huhu".parse().unwrap()];
+        pdb.update_images_transaction().for_image(&img).update_sauce(&old_sauce)
+            .tag_author(&PantsuTagAuthor::Gelbooru).add_tags(&old_tags).execute().unwrap();
+        pdb.update_images_transaction().for_image(&img).add_tags(&user_tags).execute().unwrap();
+
+        let tags = pdb.get_image_tags_transaction(&img).execute().unwrap();
+        assert!(tags.iter().all(|t| (t.tag_author == PantsuTagAuthor::User) == t.sauce.is_none()));
+        assert!(tags.iter().all(|t| PantsuTagInfo::deserialize(&t.serialize()).unwrap() == *t));
+        let old_format = PantsuTagInfo::deserialize("general:haha;gelbooru;2022-01-30 17:21:02").unwrap();
+        assert_eq!(old_format.tag, old_tags[0]);
+        assert_eq!(old_format.sauce, None);
+        let old_link = "https://gelbooru.com/index.php?id=1";
+        assert_eq!(pdb.get_image_tags_transaction(&img).with_sauce(old_link).execute().unwrap().len(), 2);
+
+        pdb.update_images_transaction().for_image(&img).update_sauce(&new_sauce)
+            .remove_sauce_tags(old_link)
+            .tag_author(&PantsuTagAuthor::Gelbooru).add_tags(&new_tags).execute().unwrap();
+        let tags = pdb.get_image_tags_transaction(&img).execute().unwrap();
+        assert_eq!(tags.len(), 3);
+        assert!(tags.iter().all(|t| t.sauce.as_deref() != Some(old_link)));
+        assert!(tags.iter().any(|t| t.tag == user_tags[0] && t.sauce.is_none()));
+        assert!(pdb.get_tags_transaction().execute().unwrap().iter().all(|t| t != &old_tags[1]));
+    }
+
+    #[test]
+    #[serial]
+    fn db_migrate_tag_sauces() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        let link = "https://gelbooru.com/index.php?id=1";
+        let booru_tags = vec!["general:Haha".parse().unwrap(), "artist:Hehe".parse().unwrap()];
+        let user_tags = vec!["general:Huhu".parse().unwrap()];
+        pdb.update_images_transaction().for_image(&img).update_sauce(&Sauce::Match(sauce::url_from_str(link).unwrap()))
+            .tag_author(&PantsuTagAuthor::Gelbooru).add_tags(&booru_tags).execute().unwrap();
+        pdb.update_images_transaction().for_image(&img).add_tags(&user_tags).execute().unwrap();
+        // a version 7 database with booru tags that were imported without their sauce
+        pdb.conn.execute("UPDATE image_tags SET sauce = NULL", []).unwrap();
+        pdb.conn.pragma_update(None, "user_version", 7).unwrap();
+        drop(pdb);
+
+        let pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        assert_eq!(pdb.get_db_version().unwrap(), 9);
+        let tags = pdb.get_image_tags_transaction(&img).execute().unwrap();
+        assert_eq!(tags.len(), 3);
+        assert!(tags.iter().all(|t| match t.tag_author {
+            PantsuTagAuthor::User => t.sauce.is_none(),
+            _ => t.sauce.as_deref() == Some(link),
+        }));
+        assert_eq!(pdb.get_image_tags_transaction(&img).with_sauce(link).execute().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn db_keep_sauce_tags() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        let old_link = "https://gelbooru.com/index.php?id=1";
+        let new_link = "https://danbooru.donmai.us/posts/2";
+        let old_tags = vec!["general:Haha".parse().unwrap(), "artist:Hehe".parse().unwrap()];
+        let new_tags = vec!["general:Haha".parse().unwrap(), "general:Huhu".parse().unwrap()];
+        let user_tags = vec!["general:Huhu".parse().unwrap()];
+        pdb.update_images_transaction().for_image(&img).update_sauce(&Sauce::Match(sauce::url_from_str(old_link).unwrap()))
+            .tag_author(&PantsuTagAuthor::Gelbooru).add_tags(&old_tags).execute().unwrap();
+        pdb.update_images_transaction().for_image(&img).add_tags(&user_tags).execute().unwrap();
+
+        // the old tags are kept, but the ones found again belong to the new sauce
+        pdb.update_images_transaction().for_image(&img).update_sauce(&Sauce::Match(sauce::url_from_str(new_link).unwrap()))
+            .tag_author(&PantsuTagAuthor::Danbooru).add_tags(&new_tags).execute().unwrap();
+        let tags = pdb.get_image_tags_transaction(&img).execute().unwrap();
+        assert_eq!(tags.len(), 3);
+        let tag = |t: &PantsuTag| tags.iter().find(|info| info.tag == *t).unwrap();
+        assert_eq!(tag(&old_tags[0]).sauce.as_deref(), Some(new_link));
+        assert_eq!(tag(&old_tags[0]).tag_author, PantsuTagAuthor::Danbooru);
+        assert_eq!(tag(&old_tags[1]).sauce.as_deref(), Some(old_link));
+        assert_eq!(tag(&old_tags[1]).tag_author, PantsuTagAuthor::Gelbooru);
+        assert_eq!(tag(&user_tags[0]).sauce, None);
+        assert_eq!(tag(&user_tags[0]).tag_author, PantsuTagAuthor::User);
+        assert_eq!(pdb.get_image_tags_transaction(&img).with_sauce(new_link).execute().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn db_tag_statistics() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        let tags: Vec<PantsuTag> = vec!["general:Haha".parse().unwrap(), "artist:Hehe".parse().unwrap()];
+        let tags2: Vec<PantsuTag> = vec!["general:Haha".parse().unwrap(), "character:Hihi".parse().unwrap()];
+        pdb.update_images_transaction().for_image(&img).add_tags(&tags).execute().unwrap();
+        pdb.update_images_transaction().for_image(&img2).add_tags(&tags2).execute().unwrap();
+
+        let counts = pdb.get_tag_counts_transaction().execute().unwrap();
+        assert_eq!(counts.len(), 3);
+        assert!(counts.iter().any(|c| c.tag == tags[0] && c.count == 2));
+        assert!(counts.iter().any(|c| c.tag == tags[1] && c.count == 1));
+        let counts = pdb.get_tag_counts_transaction().with_types(&vec![PantsuTagType::Character]).execute().unwrap();
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].tag, tags2[1]);
+
+        let related = pdb.get_related_tags_transaction().for_tag(&tags[0]).execute().unwrap();
+        assert_eq!(related.len(), 2);
+        assert!(related.iter().all(|r| r.cooccurrences == 1 && r.count == 1 && r.frequency == 0.5));
+        let related = pdb.get_related_tags_transaction().for_tags(&tags2).execute().unwrap();
+        assert!(related.is_empty());
+        let related = pdb.get_related_tags_transaction().for_tag(&tags2[1]).execute().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].tag, tags[0]);
+        assert_eq!((related[0].cooccurrences, related[0].count, related[0].frequency), (1, 2, 1.0));
+    }
+
+    #[test]
+    #[serial]
+    fn db_search_tags() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        let img = get_test_image();
+        let tags: Vec<PantsuTag> = vec!["character:rem".parse().unwrap(), "character:ram".parse().unwrap(),
+            "general:long hair".parse().unwrap(), "general:re*zero".parse().unwrap()];
+        pdb.update_images_transaction().for_image(&img).add_tags(&tags).execute().unwrap();
+
+        let found = pdb.get_tags_transaction().with_prefix("re").execute().unwrap();
+        assert_eq!(found, vec![tags[0].clone(), tags[3].clone()]);
+        assert_eq!(pdb.get_tags_transaction().with_prefix("re*").execute().unwrap(), vec![tags[3].clone()]);
+        assert_eq!(pdb.get_tags_transaction().with_prefix("character:r").execute().unwrap().len(), 2);
+        assert_eq!(pdb.get_tags_transaction().matching("*hair").execute().unwrap(), vec![tags[2].clone()]);
+        let found = pdb.get_tags_transaction().matching("r?m").with_types(&vec![PantsuTagType::Character]).execute().unwrap();
+        assert_eq!(found.len(), 2);
+
+        let similar = pdb.get_similar_tags_transaction("charcter:rem").limit(1).execute().unwrap();
+        assert_eq!(similar, vec![tags[0].clone()]);
+        let similar = pdb.get_similar_tags_transaction("Rem").max_distance(1).execute().unwrap();
+        assert_eq!(similar, vec![tags[0].clone(), tags[1].clone()]);
+        assert!(pdb.get_similar_tags_transaction("short hair").max_distance(2).execute().unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn db_normalize_tags() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        // rows of an older database, tags created now would already be normalized
+        let raw_tag = |tag_name: &str, tag_type| PantsuTag { tag_name: tag_name.to_string(), tag_type };
+        pdb.update_images_transaction().for_image(&img)
+            .add_tags(&vec![raw_tag("Stuffed_Dinosaur", PantsuTagType::General), raw_tag("stuffed dinosaur", PantsuTagType::General),
+                raw_tag("Questionable", PantsuTagType::Rating)])
+            .execute().unwrap();
+        pdb.update_images_transaction().for_image(&img2)
+            .add_tags(&vec![raw_tag(" stuffed  dinosaur ", PantsuTagType::General), raw_tag("rem", PantsuTagType::Character)])
+            .execute().unwrap();
+
+        assert_eq!(pdb.normalize_tags(&TagNormalizer::default()).unwrap(), (3, Vec::new()));
+        let stuffed_dinosaur: PantsuTag = "general:Stuffed_Dinosaur".parse().unwrap();
+        assert_eq!(stuffed_dinosaur.tag_name, "stuffed dinosaur");
+        let rating = PantsuTag::new(String::from("Questionable"), PantsuTagType::Rating);
+        let all_tags = pdb.get_tags_transaction().execute().unwrap();
+        assert_eq!(all_tags.len(), 3);
+        assert!(all_tags.contains(&stuffed_dinosaur) && all_tags.contains(&rating));
+        assert_eq!(pdb.get_image_tags_transaction(&img).execute().unwrap().len(), 2);
+        let img2_tags: Vec<PantsuTag> = pdb.get_image_tags_transaction(&img2).execute().unwrap().into_iter().map(|t| t.tag).collect();
+        assert_eq!(img2_tags.len(), 2);
+        assert!(img2_tags.contains(&stuffed_dinosaur));
+        assert_eq!(pdb.normalize_tags(&TagNormalizer::default()).unwrap(), (0, Vec::new()));
+    }
+
+    #[test]
+    #[serial]
+    fn db_normalize_tags_collision() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        let raw_tag = |tag_name: &str, tag_type| PantsuTag { tag_name: tag_name.to_string(), tag_type };
+        let character = raw_tag("Foo", PantsuTagType::Character);
+        pdb.update_images_transaction().for_image(&img)
+            .add_tags(&vec![character.clone(), raw_tag("foo", PantsuTagType::General)])
+            .execute().unwrap();
+        pdb.update_images_transaction().for_image(&img2)
+            .add_tags(&vec![character.clone()])
+            .execute().unwrap();
+
+        // the image with both tags keeps the character tag under its old name, the other image is renamed
+        let (renamed, collisions) = pdb.normalize_tags(&TagNormalizer::default()).unwrap();
+        assert_eq!(renamed, 0);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].filename, img.get_filename());
+        assert_eq!(collisions[0].tag, character);
+        assert_eq!(collisions[0].normalized_name, "foo");
+        let img_tags: Vec<PantsuTag> = pdb.get_image_tags_transaction(&img).execute().unwrap().into_iter().map(|t| t.tag).collect();
+        assert_eq!(img_tags.len(), 2);
+        assert!(img_tags.contains(&character) && img_tags.contains(&raw_tag("foo", PantsuTagType::General)));
+        let img2_tags: Vec<PantsuTag> = pdb.get_image_tags_transaction(&img2).execute().unwrap().into_iter().map(|t| t.tag).collect();
+        assert_eq!(img2_tags, vec![raw_tag("foo", PantsuTagType::Character)]);
+        assert_eq!(pdb.get_tags_transaction().execute().unwrap().len(), 3);
+    }
+
+    #[test]
+    #[serial]
+    fn db_image_ratings() {
+        let mut pdb = get_pantsu_db(Some(std::env::current_dir().unwrap().as_path())).unwrap();
+        pdb.clear().unwrap();
+        add_test_image(&mut pdb).unwrap();
+        add_test_image2(&mut pdb).unwrap();
+        add_test_image3(&mut pdb).unwrap();
+        let img = get_test_image();
+        let img2 = get_test_image2();
+        pdb.update_images_transaction().for_image(&img).add_tags(&vec![Rating::Explicit.to_tag()]).execute().unwrap();
+        pdb.update_images_transaction().for_image(&img2).add_tags(&vec!["rating:Sensitive".parse().unwrap()]).execute().unwrap();
+
+        assert_eq!(pdb.get_image_transaction(&img).execute().unwrap().unwrap().get_rating(), Some(Rating::Explicit));
+        assert_eq!(pdb.get_image_transaction(&img2).execute().unwrap().unwrap().get_rating(), Some(Rating::Sensitive));
+        assert_eq!(pdb.get_images_transaction().execute().unwrap().len(), 3);
+        // the unrated third image is left out as well
+        let images = pdb.get_images_transaction().max_rating(Some(Rating::Questionable)).execute().unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].get_image(), &img2);
+        assert!(pdb.get_images_transaction().max_rating(Some(Rating::General)).execute().unwrap().is_empty());
+        assert_eq!(pdb.get_images_transaction().max_rating(Some(Rating::Explicit)).execute().unwrap().len(), 2);
+
+        // the max rating of the database applies to every selection unless it is overridden
+        let pdb = pdb.with_max_rating(Some(Rating::Questionable));
+        assert_eq!(pdb.get_images_transaction().execute().unwrap().len(), 1);
+        assert!(pdb.get_images_transaction().with_not_checked_sauce().execute().unwrap().iter().all(|i| i.get_image() != &img));
+        assert_eq!(pdb.get_images_transaction().max_rating(None).execute().unwrap().len(), 3);
+        let mut pdb = pdb.with_max_rating(None);
+
+        pdb.update_images_transaction().for_image(&img).remove_tags(&vec![Rating::Explicit.to_tag()]).execute().unwrap();
+        assert_eq!(pdb.get_image_transaction(&img).execute().unwrap().unwrap().get_rating(), None);
+    }
+
     fn get_pantsu_db(path: Option<&Path>) -> Result<PantsuDB, Error> {
         match path {
             None => PantsuDB::new(&std::env::current_dir().unwrap().as_path().join("pantsu_tags.db")),
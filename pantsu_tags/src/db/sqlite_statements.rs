@@ -20,21 +20,188 @@ pub const DB_INIT_TABLES: &str =
             tag_type TEXT NOT NULL,
             tag_author TEXT NOT NULL,
             date_added TEXT NOT NULL,
+            sauce TEXT,
             PRIMARY KEY(filename, tag),
             FOREIGN KEY(filename) REFERENCES images(filename),
             FOREIGN KEY(tag, tag_type) REFERENCES tags(tag, tag_type)
+    );
+    CREATE TABLE IF NOT EXISTS image_aliases (
+            alias TEXT PRIMARY KEY,
+            filename TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );
+    CREATE TABLE IF NOT EXISTS sauce_candidates (
+            filename TEXT NOT NULL,
+            link TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            similarity INT NOT NULL,
+            res_width INT NOT NULL,
+            res_height INT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, link, date_added),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );
+    CREATE TABLE IF NOT EXISTS sauce_lookups (
+            filename TEXT NOT NULL,
+            judge_policy TEXT,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, date_added),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );
+    CREATE TABLE IF NOT EXISTS dead_sauces (
+            filename TEXT NOT NULL,
+            link TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, link),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );
+    CREATE TABLE IF NOT EXISTS image_sources (
+            filename TEXT NOT NULL,
+            url TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, url),
+            FOREIGN KEY(filename) REFERENCES images(filename)
     );";
 
+// update database statements
+pub const DB_UPDATE_1_2: &str =
+    "CREATE TABLE IF NOT EXISTS image_aliases (
+            alias TEXT PRIMARY KEY,
+            filename TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );";
+
+pub const DB_UPDATE_2_3: &str =
+    "CREATE TABLE IF NOT EXISTS sauce_candidates (
+            filename TEXT NOT NULL,
+            link TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            similarity INT NOT NULL,
+            res_width INT NOT NULL,
+            res_height INT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, link, date_added),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );";
+
+pub const DB_UPDATE_3_4: &str =
+    "CREATE TABLE IF NOT EXISTS sauce_lookups (
+            filename TEXT NOT NULL,
+            judge_policy TEXT,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, date_added),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );";
+
+pub const DB_UPDATE_4_5: &str =
+    "CREATE TABLE IF NOT EXISTS dead_sauces (
+            filename TEXT NOT NULL,
+            link TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, link),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );";
+
+// the sauces found so far are the first booru post sources
+pub const DB_UPDATE_5_6: &str =
+    "CREATE TABLE IF NOT EXISTS image_sources (
+            filename TEXT NOT NULL,
+            url TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            date_added TEXT NOT NULL,
+            PRIMARY KEY(filename, url),
+            FOREIGN KEY(filename) REFERENCES images(filename)
+    );
+    INSERT OR IGNORE INTO image_sources (filename, url, kind, date_added)
+        SELECT filename, image_source, 'booru_post', date_modified
+        FROM images
+        WHERE image_source_type = 'EXISTING';";
+
+// tags of a booru were found on the current sauce so far
+pub const DB_UPDATE_6_7: &str =
+    "ALTER TABLE image_tags ADD COLUMN sauce TEXT;
+    UPDATE image_tags
+    SET sauce = (
+        SELECT image_source
+        FROM images
+        WHERE images.filename = image_tags.filename
+        AND image_source_type = 'EXISTING'
+    )
+    WHERE tag_author != 'user';";
+
+// booru tags that were added without a sauce, e.g. by an import, were found on the current sauce as well
+pub const DB_UPDATE_8_9: &str =
+    "UPDATE image_tags
+    SET sauce = (
+        SELECT image_source
+        FROM images
+        WHERE images.filename = image_tags.filename
+        AND image_source_type = 'EXISTING'
+    )
+    WHERE tag_author != 'user'
+    AND sauce IS NULL;";
+
 // select statements
 pub const SELECT_IMAGE: &str =
-    "SELECT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified
+    "SELECT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
+    FROM images
+    WHERE filename = (?)";
+
+pub const SELECT_IMAGE_BY_ALIAS: &str =
+    "SELECT images.filename, image_source_type, image_source, res_width, res_height, images.date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
     FROM images
+    JOIN image_aliases ON images.filename = image_aliases.filename
+    WHERE image_aliases.alias = (?)";
+
+pub const SELECT_SAUCE_CANDIDATES: &str =
+    "SELECT link, provider, similarity, res_width, res_height, date_added
+    FROM sauce_candidates
+    WHERE filename = (?)
+    ORDER BY date_added DESC, similarity DESC";
+
+pub const SELECT_SAUCE_LOOKUPS: &str =
+    "SELECT judge_policy, date_added
+    FROM sauce_lookups
+    WHERE filename = (?)
+    ORDER BY date_added DESC";
+
+pub const SELECT_SAUCE_LOOKUP_SUMMARY: &str =
+    "SELECT COUNT(*), MAX(date_added)
+    FROM sauce_lookups
+    WHERE filename = (?)";
+
+pub const SELECT_DEAD_SAUCES: &str =
+    "SELECT link
+    FROM dead_sauces
     WHERE filename = (?)";
 
+pub const SELECT_IMAGES_WITH_TAG: &str =
+    "SELECT filename
+    FROM image_tags
+    WHERE tag = (?)
+    AND tag_type = (?)";
+
+pub const SELECT_IMAGE_SOURCES: &str =
+    "SELECT url, kind
+    FROM image_sources
+    WHERE filename = (?)
+    ORDER BY date_added";
+
+pub const SELECT_ALL_IMAGE_SOURCES: &str =
+    "SELECT filename, url, kind
+    FROM image_sources
+    ORDER BY date_added";
+
 pub const SELECT_IMAGES_SORT_BY: &str = "SORT_ORDER";
 pub const SAUCE_TYPE_PLACEHOLDER: &str = "SAUCE_TYPE";
 pub const SELECT_ALL_IMAGES: &str =
-    "SELECT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified
+    "SELECT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
     FROM images
     WHERE image_source_type LIKE 'SAUCE_TYPE'
     ORDER BY SORT_ORDER";
@@ -42,7 +209,8 @@ pub const SELECT_ALL_IMAGES: &str =
 pub const SELECT_IMAGES_FOR_TAGS_TAG_COUNT: &str= "TAG_COUNT";
 pub const SELECT_IMAGES_FOR_INCLUDING_TAGS_PLACEHOLDER: &str = "INCLUDE_TAG_LIST";
 pub const SELECT_IMAGES_FOR_INCLUDING_TAGS: &str =
-    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified
+    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
     FROM images
     WHERE filename IN (
         SELECT filename
@@ -56,7 +224,8 @@ pub const SELECT_IMAGES_FOR_INCLUDING_TAGS: &str =
 
 pub const SELECT_IMAGES_FOR_EXCLUDING_TAGS_PLACEHOLDER: &str = "EXCLUDE_TAG_LIST";
 pub const SELECT_IMAGES_FOR_EXCLUDING_TAGS: &str =
-    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified
+    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
     FROM images
     WHERE filename NOT IN (
         SELECT filename
@@ -69,7 +238,8 @@ pub const SELECT_IMAGES_FOR_EXCLUDING_TAGS: &str =
     ORDER BY SORT_ORDER";
 
 pub const SELECT_IMAGES_FOR_INCLUDING_AND_EXCLUDING_TAGS: &str =
-    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified
+    "SELECT DISTINCT filename, image_source_type, image_source, res_width, res_height, date_added, date_modified,
+        (SELECT group_concat(tag) FROM image_tags WHERE image_tags.filename = images.filename AND image_tags.tag_type = 'rating')
     FROM images
     WHERE filename IN (
         SELECT filename
@@ -90,9 +260,9 @@ pub const SELECT_IMAGES_FOR_INCLUDING_AND_EXCLUDING_TAGS: &str =
 
 pub const SELECT_TAGS_SORT_BY: &str = "SORT_ORDER";
 pub const SELECT_TAGS_FOR_IMAGE: &str =
-    "SELECT tags.tag, tags.tag_type, image_tags.tag_author, image_tags.date_added
+    "SELECT tags.tag, tags.tag_type, image_tags.tag_author, image_tags.date_added, image_tags.sauce
     FROM image_tags
-    JOIN tags ON image_tags.tag = tags.tag
+    JOIN tags ON image_tags.tag = tags.tag AND image_tags.tag_type = tags.tag_type
     WHERE image_tags.filename = (?)
     ORDER BY SORT_ORDER";
 
@@ -108,10 +278,52 @@ pub const SELECT_TAGS_WITH_TYPE: &str =
     WHERE tag_type IN (TAG_TYPE_LIST)
     ORDER BY tag_type ASC, tag ASC";
 
+// matches the tag name or the tag with its type, e.g. 'char*' or 'character:*'
+pub const SELECT_TAGS_MATCHING_GLOB: &str =
+    "SELECT tag, tag_type
+    FROM tags
+    WHERE tag GLOB (?1) OR tag_type || ':' || tag GLOB (?1)
+    ORDER BY tag_type ASC, tag ASC";
+
+pub const SELECT_TAG_COUNTS: &str =
+    "SELECT tags.tag, tags.tag_type, COUNT(image_tags.filename)
+    FROM tags
+    LEFT JOIN image_tags ON image_tags.tag = tags.tag AND image_tags.tag_type = tags.tag_type
+    GROUP BY tags.tag, tags.tag_type
+    ORDER BY tags.tag_type ASC, tags.tag ASC";
+
+pub const SELECT_TAG_COUNTS_WITH_TYPE: &str =
+    "SELECT tags.tag, tags.tag_type, COUNT(image_tags.filename)
+    FROM tags
+    LEFT JOIN image_tags ON image_tags.tag = tags.tag AND image_tags.tag_type = tags.tag_type
+    WHERE tags.tag_type IN (TAG_TYPE_LIST)
+    GROUP BY tags.tag, tags.tag_type
+    ORDER BY tags.tag_type ASC, tags.tag ASC";
+
+// the tags on the images with all tags of the query, with their number of co-occurrences,
+// their total number of images and the number of images of the query
+pub const SELECT_RELATED_TAGS: &str =
+    "WITH query_images AS (
+        SELECT filename
+        FROM image_tags
+        WHERE tag_type || ':' || tag IN (INCLUDE_TAG_LIST)
+        GROUP BY filename
+        HAVING COUNT(tag)=TAG_COUNT
+    )
+    SELECT tags.tag, tags.tag_type, COUNT(image_tags.filename),
+        (SELECT COUNT(*) FROM image_tags AS all_image_tags WHERE all_image_tags.tag = tags.tag AND all_image_tags.tag_type = tags.tag_type),
+        (SELECT COUNT(*) FROM query_images)
+    FROM image_tags
+    JOIN tags ON image_tags.tag = tags.tag AND image_tags.tag_type = tags.tag_type
+    WHERE image_tags.filename IN query_images
+    AND image_tags.tag_type || ':' || image_tags.tag NOT IN (INCLUDE_TAG_LIST)
+    GROUP BY tags.tag, tags.tag_type
+    ORDER BY COUNT(image_tags.filename) DESC, tags.tag_type ASC, tags.tag ASC";
+
 pub const SELECT_TAGS_FOR_IMAGE_WITH_TYPE: &str =
-    "SELECT tags.tag, tags.tag_type, image_tags.tag_author, image_tags.date_added
+    "SELECT tags.tag, tags.tag_type, image_tags.tag_author, image_tags.date_added, image_tags.sauce
     FROM image_tags
-    JOIN tags ON image_tags.tag = tags.tag
+    JOIN tags ON image_tags.tag = tags.tag AND image_tags.tag_type = tags.tag_type
     WHERE image_tags.filename = (?)
     AND tags.tag_type IN (TAG_TYPE_LIST)
     ORDER BY SORT_ORDER";
@@ -124,7 +336,38 @@ pub const INSERT_IMAGE_INTO_IMAGES: &str =
     "INSERT INTO images (filename, image_source_type, image_source, res_width, res_height, date_added, date_modified) VALUES (?, ?, ?, ?, ?, ?, ?)";
 
 pub const INSERT_TAG_FOR_IMAGE: &str =
-    "INSERT OR IGNORE INTO image_tags (filename, tag, tag_type, tag_author, date_added) VALUES (?, ?, ?, ?, ?)";
+    "INSERT OR IGNORE INTO image_tags (filename, tag, tag_type, tag_author, date_added, sauce) VALUES (?, ?, ?, ?, ?, ?)";
+
+// a booru tag that is already on the image moves to the sauce it was found on again, tags of the user stay theirs
+pub const UPDATE_SAUCE_OF_IMAGE_TAG: &str =
+    "UPDATE image_tags
+    SET tag_author = (?),
+        sauce = (?)
+    WHERE filename = (?)
+    AND tag = (?)
+    AND tag_type = (?)
+    AND tag_author != 'user'";
+
+pub const INSERT_TAGS_FROM_IMAGE: &str =
+    "INSERT OR IGNORE INTO image_tags (filename, tag, tag_type, tag_author, date_added, sauce)
+    SELECT (?), tag, tag_type, tag_author, date_added, sauce
+    FROM image_tags
+    WHERE filename = (?)";
+
+pub const INSERT_IMAGE_ALIAS: &str =
+    "INSERT OR REPLACE INTO image_aliases (alias, filename, date_added) VALUES (?, ?, ?)";
+
+pub const INSERT_SAUCE_CANDIDATE: &str =
+    "INSERT OR REPLACE INTO sauce_candidates (filename, link, provider, similarity, res_width, res_height, date_added) VALUES (?, ?, ?, ?, ?, ?, ?)";
+
+pub const INSERT_SAUCE_LOOKUP: &str =
+    "INSERT OR REPLACE INTO sauce_lookups (filename, judge_policy, date_added) VALUES (?, ?, ?)";
+
+pub const INSERT_DEAD_SAUCE: &str =
+    "INSERT OR REPLACE INTO dead_sauces (filename, link, date_added) VALUES (?, ?, ?)";
+
+pub const INSERT_IMAGE_SOURCE: &str =
+    "INSERT OR IGNORE INTO image_sources (filename, url, kind, date_added) VALUES (?, ?, ?, ?)";
 
 // delete statements
 pub const DELETE_UNUSED_TAGS: &str =
@@ -142,9 +385,46 @@ pub const DELETE_IMAGE_FROM_IMAGES: &str =
 pub const DELETE_TAG_FROM_IMAGES: &str =
     "DELETE FROM image_tags WHERE filename=(?) AND tag_type=(?) AND tag=(?)";
 
+// the old tag of images that already had the renamed tag, the images keep their entry of the renamed tag
+pub const DELETE_MERGED_TAG_FROM_IMAGES: &str =
+    "DELETE FROM image_tags
+    WHERE tag = (?1)
+    AND tag_type = (?3)
+    AND EXISTS (
+        SELECT 1
+        FROM image_tags AS merged
+        WHERE merged.filename = image_tags.filename
+        AND merged.tag = (?2)
+        AND merged.tag_type = (?3)
+    )";
+
+pub const DELETE_TAG_FROM_TAG_LIST: &str =
+    "DELETE FROM tags WHERE tag=(?) AND tag_type=(?)";
+
+pub const DELETE_SAUCE_TAGS_FROM_IMAGE: &str =
+    "DELETE FROM image_tags WHERE filename=(?) AND sauce=(?)";
+
 pub const DELETE_ALL_TAGS_FROM_IMAGE: &str =
     "DELETE FROM image_tags WHERE filename=(?)";
 
+pub const DELETE_ALIASES_OF_IMAGE: &str =
+    "DELETE FROM image_aliases WHERE filename=(?)";
+
+pub const DELETE_SAUCE_CANDIDATES_OF_IMAGE: &str =
+    "DELETE FROM sauce_candidates WHERE filename=(?)";
+
+pub const DELETE_SAUCE_LOOKUPS_OF_IMAGE: &str =
+    "DELETE FROM sauce_lookups WHERE filename=(?)";
+
+pub const DELETE_DEAD_SAUCE: &str =
+    "DELETE FROM dead_sauces WHERE filename=(?) AND link=(?)";
+
+pub const DELETE_DEAD_SAUCES_OF_IMAGE: &str =
+    "DELETE FROM dead_sauces WHERE filename=(?)";
+
+pub const DELETE_IMAGE_SOURCES_OF_IMAGE: &str =
+    "DELETE FROM image_sources WHERE filename=(?)";
+
 // update statements
 pub const UPDATE_IMAGE_SOURCE: &str =
     "UPDATE images
@@ -152,6 +432,38 @@ pub const UPDATE_IMAGE_SOURCE: &str =
         image_source = (?)
     WHERE filename = (?)";
 
+// images that already have the new tag keep their own entry of it
+pub const UPDATE_TAG_NAME_OF_IMAGES: &str =
+    "UPDATE OR IGNORE image_tags
+    SET tag = (?)
+    WHERE tag = (?)
+    AND tag_type = (?)";
+
+pub const UPDATE_IMAGE_ALIASES: &str =
+    "UPDATE image_aliases
+    SET filename = (?)
+    WHERE filename = (?)";
+
+pub const UPDATE_SAUCE_CANDIDATES: &str =
+    "UPDATE OR REPLACE sauce_candidates
+    SET filename = (?)
+    WHERE filename = (?)";
+
+pub const UPDATE_SAUCE_LOOKUPS: &str =
+    "UPDATE OR REPLACE sauce_lookups
+    SET filename = (?)
+    WHERE filename = (?)";
+
+pub const UPDATE_DEAD_SAUCES: &str =
+    "UPDATE OR REPLACE dead_sauces
+    SET filename = (?)
+    WHERE filename = (?)";
+
+pub const UPDATE_IMAGE_SOURCES_FILENAME: &str =
+    "UPDATE OR REPLACE image_sources
+    SET filename = (?)
+    WHERE filename = (?)";
+
 pub const UPDATE_IMAGE_DATE_MODIFIED: &str =
     "Update images
     SET date_modified = (?)
@@ -164,3 +476,14 @@ pub const CLEAR_IMAGE_TAGS: &str =
     "DELETE FROM image_tags";
 pub const CLEAR_TAGS: &str =
     "DELETE FROM tags";
+pub const CLEAR_IMAGE_ALIASES: &str =
+    "DELETE FROM image_aliases";
+
+pub const CLEAR_SAUCE_CANDIDATES: &str =
+    "DELETE FROM sauce_candidates";
+pub const CLEAR_SAUCE_LOOKUPS: &str =
+    "DELETE FROM sauce_lookups";
+pub const CLEAR_DEAD_SAUCES: &str =
+    "DELETE FROM dead_sauces";
+pub const CLEAR_IMAGE_SOURCES: &str =
+    "DELETE FROM image_sources";
@@ -40,6 +40,10 @@ impl<'a> DeleteImagesTransaction<'a> {
             match db_image {
                 Some(_) => {
                     db_calls::remove_all_tags_from_image(&transaction, arg_image)?;
+                    db_calls::remove_aliases_of_image(&transaction, arg_image)?;
+                    db_calls::remove_sauce_lookups_of_image(&transaction, arg_image)?;
+                    db_calls::remove_dead_sauces_of_image(&transaction, arg_image)?;
+                    db_calls::remove_image_sources_of_image(&transaction, arg_image)?;
                     db_calls::remove_image_from_images(&transaction, arg_image)?;
                     count += 1;
                 },
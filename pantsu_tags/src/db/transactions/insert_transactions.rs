@@ -1,6 +1,6 @@
 use rusqlite::Connection;
 use crate::db::db_calls;
-use crate::{ImageHandle};
+use crate::{ImageHandle, SauceJudgePolicy, SauceMatch};
 use crate::error::{Result};
 
 pub struct InsertImagesTransaction<'a> {
@@ -31,4 +31,48 @@ impl<'a> InsertImagesTransaction<'a> {
         transaction.commit()?;
         Ok(count)
     }
-}
\ No newline at end of file
+}
+
+//#####################################################################################
+
+pub struct InsertSauceCandidatesTransaction<'a> {
+    connection: &'a mut Connection,
+    image: &'a ImageHandle,
+    sauce_matches: Vec<&'a SauceMatch>,
+    judge_policy: Option<&'a SauceJudgePolicy>,
+}
+
+impl<'a> InsertSauceCandidatesTransaction<'a> {
+    pub(crate) fn new(connection: &'a mut Connection, image: &'a ImageHandle) -> Self {
+        InsertSauceCandidatesTransaction {
+            connection,
+            image,
+            sauce_matches: vec![],
+            judge_policy: None,
+        }
+    }
+
+    pub fn add_candidate(mut self, sauce_match: &'a SauceMatch) -> Self {
+        self.sauce_matches.push(sauce_match);
+        self
+    }
+
+    pub fn add_candidates(mut self, sauce_matches: &'a Vec<SauceMatch>) -> Self {
+        self.sauce_matches.extend(sauce_matches);
+        self
+    }
+
+    // records the policy the candidates were judged by
+    pub fn judged_by(mut self, judge_policy: &'a SauceJudgePolicy) -> Self {
+        self.judge_policy = Some(judge_policy);
+        self
+    }
+
+    // all candidates are stored as one lookup, a lookup without candidates is recorded as well
+    pub fn execute(self) -> Result<u32> {
+        let transaction = self.connection.transaction()?;
+        db_calls::add_sauce_lookup(&transaction, self.image, &self.sauce_matches, self.judge_policy)?;
+        transaction.commit()?;
+        Ok(self.sauce_matches.len() as u32)
+    }
+}
@@ -0,0 +1,73 @@
+use rusqlite::Connection;
+use crate::common::image_info::ImageInfo;
+use crate::db::db_calls;
+use crate::error::Result;
+use crate::{Error, ImageHandle, Sauce};
+
+pub struct MergeImagesTransaction<'a> {
+    connection: &'a mut Connection,
+    keep: &'a ImageHandle,
+    discard: &'a ImageHandle,
+    keep_alias: bool,
+}
+
+impl<'a> MergeImagesTransaction<'a> {
+    pub(crate) fn new(connection: &'a mut Connection, keep: &'a ImageHandle, discard: &'a ImageHandle) -> Self {
+        MergeImagesTransaction {
+            connection,
+            keep,
+            discard,
+            keep_alias: false,
+        }
+    }
+
+    /// Remember the filename of the discarded image, so it still resolves to the kept image
+    pub fn keep_alias(mut self) -> Self {
+        self.keep_alias = true;
+        self
+    }
+
+    /// Returns the sauce the kept image has after merging
+    pub fn execute(self) -> Result<Sauce> {
+        let keep_image = db_calls::get_image(self.connection, self.keep)?
+            .ok_or_else(|| Error::ImageNotFoundInDB(self.keep.get_filename().to_string()))?;
+        let discard_image = db_calls::get_image(self.connection, self.discard)?
+            .ok_or_else(|| Error::ImageNotFoundInDB(self.discard.get_filename().to_string()))?;
+        // discarding the image would remove the kept one
+        if keep_image.get_image() == discard_image.get_image() {
+            return Err(Error::MergeIntoSelf(self.keep.get_filename().to_string()));
+        }
+        let sauce = better_sauce(&keep_image, &discard_image);
+
+        let transaction = self.connection.transaction()?;
+        db_calls::copy_tags_to_image(&transaction, self.discard, self.keep)?;
+        if sauce != keep_image.get_sauce() {
+            db_calls::update_image_source(&transaction, self.keep, sauce)?;
+        }
+        db_calls::move_image_aliases(&transaction, self.discard, self.keep)?;
+        db_calls::move_sauce_lookups(&transaction, self.discard, self.keep)?;
+        db_calls::move_dead_sauces(&transaction, self.discard, self.keep)?;
+        db_calls::move_image_sources(&transaction, self.discard, self.keep)?;
+        db_calls::remove_all_tags_from_image(&transaction, self.discard)?;
+        db_calls::remove_image_from_images(&transaction, self.discard)?;
+        if self.keep_alias {
+            db_calls::add_image_alias(&transaction, self.discard, self.keep)?;
+        }
+        db_calls::modify_image(&transaction, self.keep)?;
+        transaction.commit()?;
+        Ok(sauce.clone())
+    }
+}
+
+// a found sauce beats a checked one, which beats an unsure one, which beats an unchecked one. On a tie, the kept image wins
+fn better_sauce<'b>(keep: &'b ImageInfo, discard: &'b ImageInfo) -> &'b Sauce {
+    match (keep.get_sauce(), discard.get_sauce()) {
+        (Sauce::Match(_), _) => keep.get_sauce(),
+        (_, Sauce::Match(_)) => discard.get_sauce(),
+        (Sauce::NotExisting, _) => keep.get_sauce(),
+        (_, Sauce::NotExisting) => discard.get_sauce(),
+        (Sauce::Unsure, _) => keep.get_sauce(),
+        (_, Sauce::Unsure) => discard.get_sauce(),
+        _ => keep.get_sauce(),
+    }
+}
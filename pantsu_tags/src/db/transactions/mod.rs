@@ -10,6 +10,9 @@ pub use insert_transactions::*;
 pub(crate) mod delete_transactions;
 pub use delete_transactions::*;
 
+pub(crate) mod merge_transactions;
+pub use merge_transactions::*;
+
 /*pub trait PantsuTransaction<T> {
     fn execute(self) -> Result<T>;
 }*/
\ No newline at end of file
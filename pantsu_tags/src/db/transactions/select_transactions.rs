@@ -1,14 +1,19 @@
 use std::collections::HashSet;
 use std::iter::FromIterator;
+use std::time::Duration;
+use chrono::Utc;
 use log::warn;
 use rusqlite::Connection;
 use crate::common::image_info::ImageInfo;
-use crate::common::pantsu_tag::PantsuTagInfo;
+use crate::common::rating::Rating;
+use crate::common::pantsu_tag::{PantsuTagAuthor, PantsuTagCount, PantsuTagInfo, RelatedPantsuTag};
 use crate::db::sort::{SortOrder, TagSortOption, ImageSortOption, self};
 use crate::db::{AspectRatio, db_calls, SauceType};
-use crate::{ImageHandle, PantsuTag, PantsuTagType};
+use crate::{common, ImageHandle, PantsuTag, PantsuTagType, Sauce, SauceCandidate, SauceLookup};
 use crate::error::Result;
 
+const DEFAULT_MAX_TAG_DISTANCE: usize = 3;
+
 pub struct SelectImageTransaction<'a> {
     connection: &'a Connection,
     image: &'a ImageHandle,
@@ -24,7 +29,10 @@ impl<'a> SelectImageTransaction<'a> {
 
 //impl<'a> PantsuTransaction<Option<ImageHandle>> for SelectImageTransaction<'a> {
     pub fn execute(self) -> Result<Option<ImageInfo>> {
-        db_calls::get_image(self.connection, self.image)
+        match db_calls::get_image(self.connection, self.image)? {
+            Some(image) => Ok(Some(image)),
+            None => db_calls::get_image_by_alias(self.connection, self.image), // image could have been merged into another one
+        }
     }
 }
 
@@ -36,17 +44,25 @@ pub struct SelectImagesTransaction<'a> {
     exclude_tags: HashSet<PantsuTag>,
     ratio: AspectRatio,
     sauce_type: SauceType,
+    not_modified_for: Option<Duration>,
+    recheck_after: Option<Duration>,
+    dead_sauce_only: bool,
+    max_rating: Option<Rating>,
     sort_order: &'a SortOrder<ImageSortOption>,
 }
 
 impl<'a> SelectImagesTransaction<'a> {
-    pub(crate) fn new(connection: &'a Connection) -> Self {
+    pub(crate) fn new(connection: &'a Connection, max_rating: Option<Rating>) -> Self {
         SelectImagesTransaction {
             connection,
             include_tags: HashSet::new(),
             exclude_tags: HashSet::new(),
             ratio: AspectRatio::Any,
             sauce_type: SauceType::Any,
+            not_modified_for: None,
+            recheck_after: None,
+            dead_sauce_only: false,
+            max_rating,
             sort_order: &sort::DEFAULT_IMAGE_SORT,
         }
     }
@@ -86,11 +102,42 @@ impl<'a> SelectImagesTransaction<'a> {
         self
     }
 
+    pub fn with_unsure_sauce(mut self) -> Self {
+        self.sauce_type = SauceType::Unsure;
+        self
+    }
+
     pub fn with_existing_sauce(mut self) -> Self {
         self.sauce_type = SauceType::Existing;
         self
     }
 
+    // only images whose tags or sauce have not been changed for this duration
+    pub fn not_modified_for(mut self, duration: Duration) -> Self {
+        self.not_modified_for = Some(duration);
+        self
+    }
+
+    // only images whose last sauce lookup is older than min_age, doubled for every further lookup.
+    // Images without a recorded lookup count as looked up once at their last modification
+    pub fn recheck_due(mut self, min_age: Duration) -> Self {
+        self.recheck_after = Some(min_age);
+        self
+    }
+
+    // only images whose sauce link was flagged as dead
+    pub fn with_dead_sauce(mut self) -> Self {
+        self.dead_sauce_only = true;
+        self
+    }
+
+    // Only images rated up to this rating, images without a rating are left out as they might be anything.
+    // None selects all images. Defaults to the max rating of the PantsuDB
+    pub fn max_rating(mut self, max_rating: Option<Rating>) -> Self {
+        self.max_rating = max_rating;
+        self
+    }
+
     pub fn sort_by(mut self, sort_order: &'a SortOrder<ImageSortOption>) -> Self {
         self.sort_order = sort_order;
         self
@@ -118,15 +165,70 @@ impl<'a> SelectImagesTransaction<'a> {
                     ratio <= (max as f64) && ratio >= (min as f64)
                 }).collect(),
         };
+        let images = match self.not_modified_for.map(chrono::Duration::from_std) {
+            None => images,
+            Some(duration) => {
+                // a duration out of range selects no images
+                let modified_before = duration.ok().and_then(|d| Utc::now().naive_utc().checked_sub_signed(d));
+                images.into_iter()
+                    .filter(|i| matches!(modified_before, Some(date) if *i.get_date_modified() < date))
+                    .collect()
+            },
+        };
+        let images = match self.recheck_after {
+            None => images,
+            Some(min_age) => {
+                let mut due_images = Vec::new();
+                for image in images {
+                    if recheck_is_due(self.connection, &image, min_age)? {
+                        due_images.push(image);
+                    }
+                }
+                due_images
+            },
+        };
+        let images = match self.dead_sauce_only {
+            false => images,
+            true => {
+                let mut dead_images = Vec::new();
+                for image in images {
+                    let link = match image.get_sauce() {
+                        Sauce::Match(url) => url.as_str(),
+                        _ => continue,
+                    };
+                    if db_calls::get_dead_sauces(self.connection, image.get_image())?.iter().any(|dead| dead == link) {
+                        dead_images.push(image);
+                    }
+                }
+                dead_images
+            },
+        };
+        let images = match self.max_rating {
+            None => images,
+            Some(max_rating) => images.into_iter()
+                .filter(|i| matches!(i.get_rating(), Some(rating) if rating <= max_rating))
+                .collect(),
+        };
         Ok(images)
     }
 }
 
+const MAX_RECHECK_BACKOFF_EXPONENT: u32 = 16;
+
+fn recheck_is_due(connection: &Connection, image: &ImageInfo, min_age: Duration) -> Result<bool> {
+    let (lookups, last_lookup) = db_calls::get_sauce_lookup_summary(connection, image.get_image())?;
+    let last_lookup = last_lookup.unwrap_or(*image.get_date_modified());
+    let backoff = 2u32.pow(lookups.saturating_sub(1).min(MAX_RECHECK_BACKOFF_EXPONENT));
+    let min_age = chrono::Duration::from_std(min_age.saturating_mul(backoff)).ok();
+    Ok(matches!(min_age.and_then(|age| Utc::now().naive_utc().checked_sub_signed(age)), Some(date) if last_lookup < date))
+}
+
 //#############################################################################################
 
 pub struct SelectTagsTransaction<'a> {
     connection: &'a Connection,
     types: HashSet<PantsuTagType>,
+    glob: Option<String>,
 }
 
 impl<'a> SelectTagsTransaction<'a> {
@@ -134,6 +236,7 @@ impl<'a> SelectTagsTransaction<'a> {
         SelectTagsTransaction {
             connection,
             types: HashSet::new(),
+            glob: None,
         }
     }
 
@@ -142,13 +245,166 @@ impl<'a> SelectTagsTransaction<'a> {
         self
     }
 
+    // tags whose name, or type and name like 'character:re', start with the prefix
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.glob = Some(format!("{}*", common::escape_glob(prefix)));
+        self
+    }
+
+    // tags whose name, or type and name, match the glob pattern, e.g. '*hair' or 'character:*'
+    pub fn matching(mut self, pattern: &str) -> Self {
+        self.glob = Some(pattern.to_string());
+        self
+    }
+
+    pub fn execute(self) -> Result<Vec<PantsuTag>> {
+        match &self.glob {
+            Some(glob) => Ok(db_calls::get_tags_matching_glob(self.connection, glob)?
+                .into_iter()
+                .filter(|tag| self.types.is_empty() || self.types.contains(&tag.tag_type))
+                .collect()),
+            None if self.types.len()==0 => db_calls::get_all_tags(self.connection),
+            None => db_calls::get_tags_with_types(self.connection, &Vec::from_iter(self.types)),
+        }
+    }
+}
+
+//#############################################################################################
+
+pub struct SelectSimilarTagsTransaction<'a> {
+    connection: &'a Connection,
+    text: &'a str,
+    max_distance: usize,
+    limit: Option<usize>,
+}
+
+impl<'a> SelectSimilarTagsTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection, text: &'a str) -> Self {
+        SelectSimilarTagsTransaction {
+            connection,
+            text,
+            max_distance: DEFAULT_MAX_TAG_DISTANCE,
+            limit: None,
+        }
+    }
+
+    // the highest edit distance of a similar tag
+    pub fn max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // sorted by edit distance, closest first. Text with a ':' is compared with type and name of the
+    // tags, other text only with the names. Case is ignored
     pub fn execute(self) -> Result<Vec<PantsuTag>> {
-        if self.types.len()==0 {
-            db_calls::get_all_tags(self.connection)
-        } else {
-            db_calls::get_tags_with_types(self.connection, &Vec::from_iter(self.types))
+        let text = self.text.to_lowercase();
+        let compare_type = text.contains(':');
+        let mut similar_tags = db_calls::get_all_tags(self.connection)?
+            .into_iter()
+            .map(|tag| {
+                let tag_text = match compare_type {
+                    true => tag.serialize(),
+                    false => tag.tag_name.clone(),
+                };
+                (common::edit_distance(&text, &tag_text.to_lowercase()), tag)
+            })
+            .filter(|(distance, _)| *distance <= self.max_distance)
+            .collect::<Vec<_>>();
+        similar_tags.sort_by(|(distance_a, tag_a), (distance_b, tag_b)| distance_a.cmp(distance_b).then_with(|| tag_a.tag_name.cmp(&tag_b.tag_name)));
+        let similar_tags = similar_tags.into_iter().map(|(_, tag)| tag);
+        Ok(match self.limit {
+            Some(limit) => similar_tags.take(limit).collect(),
+            None => similar_tags.collect(),
+        })
+    }
+}
+
+//#############################################################################################
+
+pub struct SelectTagCountsTransaction<'a> {
+    connection: &'a Connection,
+    types: HashSet<PantsuTagType>,
+}
+
+impl<'a> SelectTagCountsTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        SelectTagCountsTransaction {
+            connection,
+            types: HashSet::new(),
         }
     }
+
+    pub fn with_types(mut self, types: &Vec<PantsuTagType>) -> Self {
+        self.types.extend(types);
+        self
+    }
+
+    // unused tags are included with a count of 0
+    pub fn execute(self) -> Result<Vec<PantsuTagCount>> {
+        db_calls::get_tag_counts(self.connection, &Vec::from_iter(self.types))
+    }
+}
+
+//#############################################################################################
+
+pub struct SelectRelatedTagsTransaction<'a> {
+    connection: &'a Connection,
+    tags: HashSet<&'a PantsuTag>,
+    types: HashSet<PantsuTagType>,
+    limit: Option<usize>,
+}
+
+impl<'a> SelectRelatedTagsTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        SelectRelatedTagsTransaction {
+            connection,
+            tags: HashSet::new(),
+            types: HashSet::new(),
+            limit: None,
+        }
+    }
+
+    pub fn for_tag(mut self, tag: &'a PantsuTag) -> Self {
+        self.tags.insert(tag);
+        self
+    }
+
+    // the query matches the images with all of these tags
+    pub fn for_tags(mut self, tags: &'a Vec<PantsuTag>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    pub fn with_types(mut self, types: &Vec<PantsuTagType>) -> Self {
+        self.types.extend(types);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // sorted by the number of co-occurrences, most frequent first
+    pub fn execute(self) -> Result<Vec<RelatedPantsuTag>> {
+        if self.tags.is_empty() {
+            warn!("Querying related tags without tags");
+            return Ok(vec![]);
+        }
+        let types = self.types;
+        let related_tags = db_calls::get_related_tags(self.connection, &Vec::from_iter(self.tags))?
+            .into_iter()
+            .filter(|related| types.is_empty() || types.contains(&related.tag.tag_type));
+        Ok(match self.limit {
+            Some(limit) => related_tags.take(limit).collect(),
+            None => related_tags.collect(),
+        })
+    }
 }
 
 //#############################################################################################
@@ -158,6 +414,8 @@ pub struct SelectImageTagsTransaction<'a> {
     connection: &'a Connection,
     image: &'a ImageHandle,
     types: HashSet<PantsuTagType>,
+    author: Option<&'a PantsuTagAuthor>,
+    sauce: Option<&'a str>,
     sort_order: &'a SortOrder<TagSortOption>
 }
 
@@ -167,6 +425,8 @@ impl<'a> SelectImageTagsTransaction<'a> {
             connection,
             image: image,
             types: HashSet::new(),
+            author: None,
+            sauce: None,
             sort_order: &sort::DEFAULT_TAG_SORT
         }
     }
@@ -176,6 +436,17 @@ impl<'a> SelectImageTagsTransaction<'a> {
         self
     }
 
+    pub fn with_author(mut self, author: &'a PantsuTagAuthor) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    // only tags that were found on this sauce link
+    pub fn with_sauce(mut self, sauce_link: &'a str) -> Self {
+        self.sauce = Some(sauce_link);
+        self
+    }
+
     pub fn sort_by(mut self, sort_order: &'a SortOrder<TagSortOption>) -> Self {
         self.sort_order = sort_order;
         self
@@ -186,11 +457,16 @@ impl<'a> SelectImageTagsTransaction<'a> {
         let db_image = db_calls::get_image(self.connection, self.image)?;
         match db_image {
             Some(_) => {
-                if self.types.len()==0 {
-                    db_calls::get_tags_for_image(self.connection, self.image, self.sort_order)
+                let tags = if self.types.len()==0 {
+                    db_calls::get_tags_for_image(self.connection, self.image, self.sort_order)?
                 } else {
-                    db_calls::get_tags_for_image_with_types(self.connection, self.image, &Vec::from_iter(self.types), self.sort_order)
-                }
+                    db_calls::get_tags_for_image_with_types(self.connection, self.image, &Vec::from_iter(self.types), self.sort_order)?
+                };
+                let (author, sauce) = (self.author, self.sauce);
+                Ok(tags.into_iter()
+                    .filter(|t| author.is_none() || author == Some(&t.tag_author))
+                    .filter(|t| sauce.is_none() || t.sauce.as_deref() == sauce)
+                    .collect())
             },
             None => {
                 warn!("Querying tags for not existing image: {}", self.image.get_filename());
@@ -198,4 +474,84 @@ impl<'a> SelectImageTagsTransaction<'a> {
             },
         }
     }
-}
\ No newline at end of file
+}
+
+//#####################################################################################
+
+pub struct SelectSauceCandidatesTransaction<'a> {
+    connection: &'a Connection,
+    image: &'a ImageHandle,
+    latest_lookup_only: bool,
+}
+
+impl<'a> SelectSauceCandidatesTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection, image: &'a ImageHandle) -> Self {
+        SelectSauceCandidatesTransaction {
+            connection,
+            image,
+            latest_lookup_only: false,
+        }
+    }
+
+    pub fn latest_lookup_only(mut self) -> Self {
+        self.latest_lookup_only = true;
+        self
+    }
+
+    // newest lookups first, candidates of one lookup sorted by similarity
+    pub fn execute(self) -> Result<Vec<SauceCandidate>> {
+        let candidates = db_calls::get_sauce_candidates(self.connection, self.image)?;
+        if !self.latest_lookup_only {
+            return Ok(candidates);
+        }
+        let latest_date = match candidates.first() {
+            Some(candidate) => candidate.date_added,
+            None => return Ok(candidates),
+        };
+        Ok(candidates.into_iter()
+            .take_while(|candidate| candidate.date_added == latest_date)
+            .collect())
+    }
+}
+
+//#####################################################################################
+
+pub struct SelectSauceLookupsTransaction<'a> {
+    connection: &'a Connection,
+    image: &'a ImageHandle,
+}
+
+impl<'a> SelectSauceLookupsTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection, image: &'a ImageHandle) -> Self {
+        SelectSauceLookupsTransaction {
+            connection,
+            image,
+        }
+    }
+
+    // newest lookups first
+    pub fn execute(self) -> Result<Vec<SauceLookup>> {
+        db_calls::get_sauce_lookups(self.connection, self.image)
+    }
+}
+
+//#####################################################################################
+
+pub struct SelectDeadSaucesTransaction<'a> {
+    connection: &'a Connection,
+    image: &'a ImageHandle,
+}
+
+impl<'a> SelectDeadSaucesTransaction<'a> {
+    pub(crate) fn new(connection: &'a Connection, image: &'a ImageHandle) -> Self {
+        SelectDeadSaucesTransaction {
+            connection,
+            image,
+        }
+    }
+
+    // links of the image that were found deleted or moved
+    pub fn execute(self) -> Result<Vec<String>> {
+        db_calls::get_dead_sauces(self.connection, self.image)
+    }
+}
@@ -3,6 +3,7 @@ use std::iter::FromIterator;
 use log::warn;
 use rusqlite::Connection;
 use crate::common::image_info::ImageInfo;
+use crate::common::image_source::{ImageSource, ImageSourceKind};
 use crate::common::pantsu_tag::PantsuTagAuthor;
 use crate::db::db_calls;
 use crate::error::Result;
@@ -15,7 +16,10 @@ pub struct UpdateImagesTransaction<'a> {
     sauce: Option<&'a Sauce>,
     tags_to_add: HashSet<&'a PantsuTag>,
     tag_author: &'a PantsuTagAuthor,
+    tag_sauce: Option<&'a str>,
     tags_to_remove: HashSet<&'a PantsuTag>,
+    sauce_tags_to_remove: HashSet<&'a str>,
+    sources_to_add: HashSet<&'a ImageSource>,
 }
 
 impl<'a> UpdateImagesTransaction<'a> {
@@ -26,7 +30,10 @@ impl<'a> UpdateImagesTransaction<'a> {
             sauce: None,
             tags_to_add: HashSet::new(),
             tag_author: &PantsuTagAuthor::User,
+            tag_sauce: None,
             tags_to_remove: HashSet::new(),
+            sauce_tags_to_remove: HashSet::new(),
+            sources_to_add: HashSet::new(),
         }
     }
 
@@ -60,6 +67,12 @@ impl<'a> UpdateImagesTransaction<'a> {
         self
     }
 
+    // the sauce link the added tags were found on. Defaults to the updated sauce if the tags are not added by the user
+    pub fn tag_sauce(mut self, sauce_link: &'a str) -> Self {
+        self.tag_sauce = Some(sauce_link);
+        self
+    }
+
     pub fn remove_tag(mut self, tag: &'a PantsuTag) -> Self {
         self.tags_to_remove.insert(tag);
         self
@@ -70,6 +83,18 @@ impl<'a> UpdateImagesTransaction<'a> {
         self
     }
 
+    // removes all tags that were found on this sauce link
+    pub fn remove_sauce_tags(mut self, sauce_link: &'a str) -> Self {
+        self.sauce_tags_to_remove.insert(sauce_link);
+        self
+    }
+
+    // a matching sauce is added as booru post source on its own
+    pub fn add_sources(mut self, sources: &'a [ImageSource]) -> Self {
+        self.sources_to_add.extend(sources);
+        self
+    }
+
 //impl<'a> PantsuTransaction<()> for UpdateImagesTransaction<'a> {
     pub fn execute(self) -> Result<u32> {
         if self.images.is_empty() {
@@ -77,7 +102,8 @@ impl<'a> UpdateImagesTransaction<'a> {
             warn!("Updating 0 images");
             return Ok(0);
         }
-        if self.sauce.is_none() && self.tags_to_add.is_empty() && self.tags_to_remove.is_empty() {
+        if self.sauce.is_none() && self.tags_to_add.is_empty() && self.tags_to_remove.is_empty()
+            && self.sauce_tags_to_remove.is_empty() && self.sources_to_add.is_empty() {
             eprintln!("[UpdateImagesTransaction] warning: no update operations");
             warn!("No update operation specified");
             return Ok(0);
@@ -86,6 +112,15 @@ impl<'a> UpdateImagesTransaction<'a> {
         let tags_to_add = Vec::from_iter(self.tags_to_add.clone());
         let tags_to_remove = Vec::from_iter(self.tags_to_remove.clone());
         let images = Vec::from_iter(self.images.clone());
+        let mut sources_to_add = self.sources_to_add.iter().map(|&s| s.clone()).collect::<Vec<ImageSource>>();
+        if let Some(Sauce::Match(url)) = self.sauce {
+            sources_to_add.push(ImageSource::new(url.to_string(), ImageSourceKind::BooruPost));
+        }
+        let tag_sauce = match (self.tag_sauce, self.sauce, self.tag_author) {
+            (Some(link), _, _) => Some(link.to_string()),
+            (None, Some(Sauce::Match(url)), author) if *author != PantsuTagAuthor::User => Some(url.to_string()),
+            _ => None,
+        };
 
         let images = images.into_iter()
             .map(|i| Ok((i, db_calls::get_image(&self.connection, i)?)))
@@ -101,13 +136,22 @@ impl<'a> UpdateImagesTransaction<'a> {
             if self.sauce.is_some() {
                 db_calls::update_image_source(&transaction, arg_image, self.sauce.unwrap())?;
             }
+            if !sources_to_add.is_empty() {
+                db_calls::add_image_sources(&transaction, arg_image, &sources_to_add)?;
+            }
             if !tags_to_remove.is_empty() {
                 db_calls::remove_tags_from_images(&transaction, arg_image, &tags_to_remove)?;
                 db_calls::remove_unused_tags(&transaction)?;
             }
+            if !self.sauce_tags_to_remove.is_empty() {
+                for &sauce_link in &self.sauce_tags_to_remove {
+                    db_calls::remove_sauce_tags_from_image(&transaction, arg_image, sauce_link)?;
+                }
+                db_calls::remove_unused_tags(&transaction)?;
+            }
             if !tags_to_add.is_empty() {
                 db_calls::add_tags_to_tag_list(&transaction, &tags_to_add)?;
-                db_calls::add_tags_to_image(&transaction, arg_image, &tags_to_add, &self.tag_author)?;
+                db_calls::add_tags_to_image(&transaction, arg_image, &tags_to_add, &self.tag_author, tag_sauce.as_deref())?;
             }
             db_calls::modify_image(&transaction, arg_image)?;
             count += 1;
@@ -115,4 +159,51 @@ impl<'a> UpdateImagesTransaction<'a> {
         transaction.commit()?;
         Ok(count)
     }
-}
\ No newline at end of file
+}
+
+//#####################################################################################
+
+pub struct UpdateDeadSaucesTransaction<'a> {
+    connection: &'a mut Connection,
+    image: &'a ImageHandle,
+    dead_links: HashSet<&'a str>,
+    alive_links: HashSet<&'a str>,
+}
+
+impl<'a> UpdateDeadSaucesTransaction<'a> {
+    pub(crate) fn new(connection: &'a mut Connection, image: &'a ImageHandle) -> Self {
+        UpdateDeadSaucesTransaction {
+            connection,
+            image,
+            dead_links: HashSet::new(),
+            alive_links: HashSet::new(),
+        }
+    }
+
+    pub fn mark_dead(mut self, link: &'a str) -> Self {
+        self.dead_links.insert(link);
+        self
+    }
+
+    // e.g. if a post was only unreachable for a while
+    pub fn mark_alive(mut self, link: &'a str) -> Self {
+        self.alive_links.insert(link);
+        self
+    }
+
+    pub fn execute(self) -> Result<u32> {
+        if db_calls::get_image(self.connection, self.image)?.is_none() {
+            warn!("Trying to update dead sauces of image not in database: {}", self.image.get_filename());
+            return Ok(0);
+        }
+        let transaction = self.connection.transaction()?;
+        for link in &self.alive_links {
+            db_calls::remove_dead_sauce(&transaction, self.image, link)?;
+        }
+        for link in &self.dead_links {
+            db_calls::add_dead_sauce(&transaction, self.image, link)?;
+        }
+        transaction.commit()?;
+        Ok((self.dead_links.len() + self.alive_links.len()) as u32)
+    }
+}
@@ -36,6 +36,12 @@ pub(crate) fn calculate_fileinfo(path: &Path) -> Result<(ImageHandle, (u32, u32)
     Ok((image_handle, image.dimensions()))
 }
 
+pub(crate) fn calculate_perceptual_hash(path: &Path) -> Result<Blockhash144> {
+    let image = image::open(path)
+        .map_err(|_| Error::ImageLoadError(common::get_path(path)))?;
+    Ok(blockhash::blockhash144(&AdapterImage { image: &image }))
+}
+
 pub(crate) fn extract_hash(filename: &str) -> Result<Blockhash144> {
     let filename = filename.trim();
     if !super::filename_is_valid(filename) {
@@ -52,6 +58,10 @@ fn get_fnv1a_hash(bytes: &Vec<u8>) -> String {
     format!("{:016x}", fnv.finish())
 }
 
+pub(crate) fn get_md5_hash(bytes: &[u8]) -> String {
+    format!("{:x}", md5::compute(bytes))
+}
+
 fn get_perceptual_hash(image: &DynamicImage) -> String {
     let hash = blockhash::blockhash144(&AdapterImage { image });
     hash.to_string()
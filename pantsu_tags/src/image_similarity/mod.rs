@@ -3,10 +3,17 @@ use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 
 mod similarity;
+mod sauce_similarity;
+mod tag_suggestions;
 
 pub use similarity::group_similar_images;
+pub use sauce_similarity::{get_original_confidence, get_sauce_confidence};
+pub use tag_suggestions::{get_nearest_images, aggregate_tag_suggestions, TagSuggestion};
 use crate::ImageHandle;
 
+// hash distances from this value on give no confidence at all
+pub(crate) const MAX_RELEVANT_HASH_DISTANCE: u32 = 32;
+
 pub trait NamedImage {
     /// Returns the name generated by crate::file_handler::hash::calculate_fileinfo()
     fn get_name(&self) -> &str;
@@ -0,0 +1,51 @@
+use std::path::Path;
+use crate::common::error::{Error, Result};
+use crate::{common, ImageInfo, SauceMatch};
+use crate::file_handler::hash;
+use super::MAX_RELEVANT_HASH_DISTANCE;
+
+// relative aspect ratio deviation from which on the sauce can not be the same image
+const MAX_ASPECT_RATIO_DEVIATION: f32 = 0.1;
+
+/// Confidence in [0, 1] that the thumbnail of a sauce shows the same image as the local one.
+/// Combines the perceptual hash distance with the agreement of the aspect ratios
+pub fn get_sauce_confidence(image: &ImageInfo, sauce: &SauceMatch, thumbnail: &Path) -> Result<f32> {
+    Ok(hash_confidence(image, thumbnail)? * aspect_ratio_confidence(image.get_res(), sauce.resolution))
+}
+
+/// Confidence in [0, 1] that a downloaded original shows the same image as the local one
+pub fn get_original_confidence(image: &ImageInfo, original: &Path) -> Result<f32> {
+    let (width, height) = image::image_dimensions(original)
+        .map_err(|_| Error::ImageLoadError(common::get_path(original)))?;
+    Ok(hash_confidence(image, original)? * aspect_ratio_confidence(image.get_res(), (width as i32, height as i32)))
+}
+
+fn hash_confidence(image: &ImageInfo, other: &Path) -> Result<f32> {
+    let image_hash = hash::extract_hash(image.get_image().get_filename())?;
+    let other_hash = hash::calculate_perceptual_hash(other)?;
+    Ok(1.0 - image_hash.distance(&other_hash).min(MAX_RELEVANT_HASH_DISTANCE) as f32 / MAX_RELEVANT_HASH_DISTANCE as f32)
+}
+
+fn aspect_ratio_confidence(image_res: (u32, u32), sauce_res: (i32, i32)) -> f32 {
+    if image_res.0 == 0 || image_res.1 == 0 || sauce_res.0 <= 0 || sauce_res.1 <= 0 {
+        return 1.0; // unknown resolution, only rely on the hash
+    }
+    let image_ratio = image_res.0 as f32 / image_res.1 as f32;
+    let sauce_ratio = sauce_res.0 as f32 / sauce_res.1 as f32;
+    let deviation = (image_ratio - sauce_ratio).abs() / image_ratio;
+    (1.0 - deviation / MAX_ASPECT_RATIO_DEVIATION).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::aspect_ratio_confidence;
+
+    #[test]
+    fn test_aspect_ratio_confidence() {
+        assert_eq!(aspect_ratio_confidence((1000, 2000), (500, 1000)), 1.0);
+        assert_eq!(aspect_ratio_confidence((1000, 2000), (0, 0)), 1.0);
+        assert_eq!(aspect_ratio_confidence((1000, 2000), (2000, 1000)), 0.0);
+        let slightly_cropped = aspect_ratio_confidence((1000, 2000), (1000, 1900));
+        assert!(slightly_cropped > 0.0 && slightly_cropped < 1.0);
+    }
+}
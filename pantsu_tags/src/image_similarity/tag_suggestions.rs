@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use crate::common::error::Result;
+use crate::file_handler::hash;
+use crate::{ImageHandle, ImageInfo, PantsuTag, PantsuTagType};
+use super::MAX_RELEVANT_HASH_DISTANCE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSuggestion {
+    pub tag: PantsuTag,
+    // distance weighted share of the neighbours with this tag, in [0, 1]
+    pub confidence: f32,
+    pub neighbours: usize,
+}
+
+/// Images with their perceptual hash distance to the image, nearest first.
+/// The image itself and images too far away to give any confidence are left out
+pub fn get_nearest_images<'a>(image: &ImageHandle, other_images: &'a [ImageInfo]) -> Result<Vec<(&'a ImageInfo, u32)>> {
+    let image_hash = hash::extract_hash(image.get_filename())?;
+    let mut nearest = other_images.iter()
+        .filter(|other| other.get_image() != image)
+        .map(|other| Ok((other, image_hash.distance(&hash::extract_hash(other.get_image().get_filename())?))))
+        .collect::<Result<Vec<(&ImageInfo, u32)>>>()?
+        .into_iter()
+        .filter(|(_, distance)| *distance < MAX_RELEVANT_HASH_DISTANCE)
+        .collect::<Vec<_>>();
+    nearest.sort_by_key(|(_, distance)| *distance);
+    Ok(nearest)
+}
+
+/// Ranks the tags of the neighbours by their confidence, highest first. Every neighbour is
+/// weighted by its hash distance, only the most confident rating is suggested
+pub fn aggregate_tag_suggestions(neighbours: &[(Vec<PantsuTag>, u32)]) -> Vec<TagSuggestion> {
+    let weights = neighbours.iter()
+        .map(|(tags, distance)| (tags, 1.0 - (*distance).min(MAX_RELEVANT_HASH_DISTANCE) as f32 / MAX_RELEVANT_HASH_DISTANCE as f32))
+        .collect::<Vec<_>>();
+    let total_weight: f32 = weights.iter().map(|(_, weight)| weight).sum();
+    if total_weight <= 0.0 {
+        return Vec::new();
+    }
+    let mut scores: HashMap<&PantsuTag, (f32, usize)> = HashMap::new();
+    for (tags, weight) in weights {
+        for tag in tags {
+            let score = scores.entry(tag).or_insert((0.0, 0));
+            score.0 += weight;
+            score.1 += 1;
+        }
+    }
+    let mut suggestions = scores.into_iter()
+        .map(|(tag, (weight, neighbours))| TagSuggestion { tag: tag.clone(), confidence: weight / total_weight, neighbours })
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.tag.tag_name.cmp(&b.tag.tag_name)));
+    let mut rating_found = false;
+    suggestions.retain(|suggestion| match suggestion.tag.tag_type {
+        PantsuTagType::Rating => !std::mem::replace(&mut rating_found, true),
+        _ => true,
+    });
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PantsuTag;
+    use super::aggregate_tag_suggestions;
+
+    #[test]
+    fn test_aggregate_tag_suggestions() {
+        let tag = |text: &str| text.parse::<PantsuTag>().unwrap();
+        let neighbours = vec![
+            (vec![tag("general:1girl"), tag("character:hihi"), tag("rating:General")], 0),
+            (vec![tag("general:1girl"), tag("rating:Sensitive")], 16),
+            (vec![tag("general:solo")], 32),
+        ];
+        let suggestions = aggregate_tag_suggestions(&neighbours);
+        assert_eq!(suggestions.iter().map(|s| s.tag.to_string()).collect::<Vec<_>>(),
+            vec!["general:1girl", "rating:general", "character:hihi", "general:solo"]);
+        assert_eq!(suggestions[0].confidence, 1.0);
+        assert_eq!(suggestions[0].neighbours, 2);
+        assert!((suggestions[1].confidence - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(suggestions[3].confidence, 0.0);
+        assert!(aggregate_tag_suggestions(&[]).is_empty());
+    }
+}
@@ -8,15 +8,28 @@ pub use crate::common::error::Error;
 pub use crate::common::error::Result;
 pub use crate::common::image_handle::ImageHandle;
 pub use crate::common::image_info::ImageInfo;
-pub use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+pub use crate::common::image_source::{ImageSource, ImageSourceKind};
+pub use crate::common::pantsu_tag::{PantsuTag, PantsuTagType, PantsuTagAuthor, PantsuTagDiff, PantsuTagCount, RelatedPantsuTag, TagNameCollision};
+pub use crate::common::pantsu_tag::{TagNormalizer, set_tag_normalizer, get_tag_normalizer};
+pub use crate::common::rating::Rating;
 pub use crate::common::tmp_dir::TmpFile;
 pub use crate::sauce::Sauce;
-pub use crate::sauce::SauceMatch;
+pub use crate::sauce::{SauceMatch, SaucePost};
+pub use crate::sauce::{SauceCandidate, SauceLookup};
+pub use crate::sauce::{SauceLiveness, SauceProvider, SauceProviders, TagProvider, Md5SauceProvider, IqdbSauceProvider, GelbooruTagProvider, DanbooruTagProvider, GelbooruCredentials};
+pub use crate::sauce::{GelbooruMd5SauceProvider, DanbooruMd5SauceProvider};
+pub use crate::sauce::{SauceConfig, BooruConfig, HttpConfig, CacheConfig, HttpClient};
+pub use crate::sauce::{SauceJudgePolicy, SauceJudgement};
 pub use crate::sauce::get_thumbnails;
 pub use crate::sauce::url_from_str;
+pub use crate::image_similarity::TagSuggestion;
+pub use crate::model_tagger::ModelTaggerConfig;
+#[cfg(feature = "ai-tagger")]
+pub use crate::model_tagger::ModelTagger;
 
 mod sauce;
 mod common;
+mod model_tagger;
 pub mod image_similarity;
 pub mod db;
 pub mod file_handler;
@@ -41,22 +54,132 @@ pub fn import_image(pantsu_db: &mut PantsuDB, lib: &Path, image: &ImageToImport,
 }
 
 
-pub async fn get_image_sauces(lib: &Path, image: &ImageHandle) -> Result<Vec<SauceMatch>> {
-    let mut sauce_matches = sauce::find_sauce(image, lib).await?;
+pub async fn get_image_sauces(providers: &SauceProviders, lib: &Path, image: &ImageHandle) -> Result<Vec<SauceMatch>> {
+    Ok(lookup_image_sauces(providers, lib, image, false).await?.0)
+}
+
+// bypasses the cache if refresh is set. Also returns whether the sauces were taken from the cache,
+// as those are no new lookup of the image
+pub async fn lookup_image_sauces(providers: &SauceProviders, lib: &Path, image: &ImageHandle, refresh: bool) -> Result<(Vec<SauceMatch>, bool)> {
+    let (mut sauce_matches, cached) = match refresh {
+        true => (providers.refresh_sauce(image, lib).await?, false),
+        false => providers.find_sauce_cached(image, lib).await?,
+    };
     sauce_matches.sort();
     sauce_matches.reverse();
-    Ok(sauce_matches)
+    Ok((sauce_matches, cached))
+}
+
+// accepts, discards or defers the found sauces of an image according to the policy
+pub async fn judge_sauce(providers: &SauceProviders, policy: &SauceJudgePolicy, image: &ImageInfo, sauces: Vec<SauceMatch>) -> Result<SauceJudgement> {
+    sauce::judge_sauce(providers, policy, image, sauces, false).await
+}
+
+// like judge_sauce, but fetches the tags of the accepted sauce bypassing the cache, e.g. to replace a dead sauce
+pub async fn judge_sauce_uncached(providers: &SauceProviders, policy: &SauceJudgePolicy, image: &ImageInfo, sauces: Vec<SauceMatch>) -> Result<SauceJudgement> {
+    sauce::judge_sauce(providers, policy, image, sauces, true).await
+}
+
+pub async fn get_sauce_tags(providers: &SauceProviders, sauce: &SauceMatch) -> Result<Vec<PantsuTag>> {
+    providers.find_tags(&sauce.link).await
+}
+
+// fetches the current tags of a sauce, bypassing the cache, and compares them with the previously stored tags of that sauce
+pub async fn get_sauce_tags_diff(providers: &SauceProviders, sauce_link: &str, stored_tags: &[PantsuTag]) -> Result<PantsuTagDiff> {
+    let tags = providers.refresh_tags(sauce_link).await?;
+    Ok(PantsuTagDiff::new(stored_tags, &tags))
+}
+
+// the original file and source of the sauce post, e.g. to upgrade a downscaled local copy
+pub async fn get_sauce_post(providers: &SauceProviders, sauce_link: &str) -> Result<SaucePost> {
+    providers.find_post(sauce_link).await
+}
+
+// the sauce post and the original source given on it, to be stored with the image
+pub async fn get_sauce_sources(providers: &SauceProviders, sauce_link: &str) -> Result<Vec<ImageSource>> {
+    Ok(providers.find_post(sauce_link).await?.get_image_sources(sauce_link))
+}
+
+// the original file of the post has to be hosted by the booru of the sauce link
+pub async fn download_sauce_original(providers: &SauceProviders, sauce_link: &str, post: &SaucePost) -> Result<TmpFile> {
+    sauce::download_original(providers, sauce_link, post).await
+}
+
+// Replaces the image in the library by the given file. Tags, sauce and aliases move to the new image
+// and the old filename stays an alias of it. Returns the handle of the new image
+pub fn replace_image(pantsu_db: &mut PantsuDB, lib: &Path, image: &ImageHandle, new_file: &Path) -> Result<ImageHandle> {
+    let new_image = check_image(pantsu_db, new_file)?;
+    import::import_file(lib, &new_image.current_path, &new_image.image_handle, true)?;
+    let merged = pantsu_db.add_images_transaction().add_image(&new_image.image_handle, new_image.res).execute()
+        .and_then(|_| pantsu_db.merge_images(&new_image.image_handle, image).keep_alias().execute());
+    if let Err(e) = merged {
+        // the old image is left untouched, only the half imported new image has to go. Cleanup errors are
+        // dropped in favor of the original error
+        let _ = pantsu_db.remove_image_transaction().remove_image(&new_image.image_handle).execute();
+        let _ = std::fs::remove_file(new_image.image_handle.get_path(lib));
+        return Err(e);
+    }
+
+    let old_path = image.get_path(lib);
+    if old_path.is_file() {
+        std::fs::remove_file(&old_path)
+            .map_err(|e| Error::FileWriteError(e, common::get_path(&old_path)))?;
+    }
+    Ok(new_image.image_handle)
+}
+
+// checks if the post behind a stored sauce link was deleted or moved
+pub async fn check_sauce_liveness(providers: &SauceProviders, sauce_link: &str) -> Result<SauceLiveness> {
+    providers.check_liveness(sauce_link).await
+}
+
+// removes all cached sauce and tag lookups, returns the number of removed entries
+pub fn clear_sauce_cache(providers: &SauceProviders) -> Result<usize> {
+    providers.clear_cache()
+}
+
+// the author tags of this sauce should be stored with, e.g. PantsuTagAuthor::Gelbooru
+pub fn get_sauce_tag_author(providers: &SauceProviders, sauce: &SauceMatch) -> Result<PantsuTagAuthor> {
+    providers.tag_author(&sauce.link)
 }
 
-pub async fn get_sauce_tags(sauce: &SauceMatch) -> Result<Vec<PantsuTag>> {
-    sauce::find_tags_gelbooru(&sauce.link).await
+// Suggests tags for an image from the tags of its most similar tagged images in the library.
+// Tags the image already has are left out, as is a rating if the image already has one
+pub fn suggest_tags(pantsu_db: &PantsuDB, image: &ImageHandle, neighbours: usize) -> Result<Vec<TagSuggestion>> {
+    // only the tags of the neighbours are used, so images of all ratings are compared
+    let images = pantsu_db.get_images_transaction().max_rating(None).execute()?;
+    let mut tagged_neighbours = Vec::new();
+    for (other, distance) in image_similarity::get_nearest_images(image, &images)? {
+        if tagged_neighbours.len() == neighbours {
+            break;
+        }
+        let tags = pantsu_db.get_image_tags_transaction(other.get_image())
+            .execute()?
+            .into_iter()
+            .map(|t| t.tag)
+            .collect::<Vec<PantsuTag>>();
+        if !tags.is_empty() {
+            tagged_neighbours.push((tags, distance));
+        }
+    }
+    let image_tags = pantsu_db.get_image_tags_transaction(image)
+        .execute()?
+        .into_iter()
+        .map(|t| t.tag)
+        .collect::<Vec<PantsuTag>>();
+    Ok(image_similarity::aggregate_tag_suggestions(&tagged_neighbours)
+        .into_iter()
+        .filter(|suggestion| !image_tags.contains(&suggestion.tag))
+        .filter(|suggestion| suggestion.tag.tag_type != PantsuTagType::Rating
+            || !image_tags.iter().any(|tag| tag.tag_type == PantsuTagType::Rating))
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
     use std::path::{Path, PathBuf};
-    use crate::{PantsuDB, Sauce, sauce};
+    use crate::{PantsuDB, Sauce, SauceProviders, sauce};
     use serial_test::serial;
 
     #[tokio::test]
@@ -69,13 +192,31 @@ mod tests {
 
         let new_image = crate::check_image(&mut pdb, &image_path).unwrap();
         crate::import_image(&mut pdb, Path::new("./test_image_lib"), &new_image, true).unwrap();
-        let sauces = crate::get_image_sauces(Path::new("./test_image_lib"), &new_image.image_handle).await.unwrap();
+        let providers = SauceProviders::default();
+        let sauces = crate::get_image_sauces(&providers, Path::new("./test_image_lib"), &new_image.image_handle).await.unwrap();
         let best_match = &sauces[0];
         // in general, you would want to check the similarity here
-        let tags = crate::get_sauce_tags(&best_match).await.unwrap();
+        let tags = crate::get_sauce_tags(&providers, &best_match).await.unwrap();
         pdb.update_images_transaction().for_image(&new_image.image_handle).update_sauce(&Sauce::Match(sauce::url_from_str(&best_match.link).unwrap())).add_tags(&tags).execute().unwrap();
     }
 
+    #[test]
+    #[serial]
+    fn test_replace_image_cleanup() {
+        let mut pdb = PantsuDB::new(&std::env::current_dir().unwrap().join("pantsu_tags.db")).unwrap();
+        pdb.clear().unwrap();
+        let lib = std::env::temp_dir().join("pantsu-tags-test-replace-lib");
+        let new_file = std::env::temp_dir().join("pantsu-tags-test-replacement.png");
+        image::RgbImage::from_pixel(8, 8, image::Rgb([200, 100, 50])).save(&new_file).unwrap();
+
+        // merging into an image that is not in the database fails, which must not leave the new image behind
+        let missing_image = crate::ImageHandle::new(String::from("1b64e362cdf968d9-c1fc07e23e05e2fc0be39ce8cc88f8044fcf.jpg")).unwrap();
+        assert!(crate::replace_image(&mut pdb, &lib, &missing_image, &new_file).is_err());
+        let new_image = crate::check_image(&mut pdb, &new_file).unwrap();
+        assert!(!new_image.image_handle.get_path(&lib).exists());
+        std::fs::remove_file(new_file).unwrap();
+    }
+
     // todo: this test does not really make sense anymore, since import_image() will always succeed even if images are similar. Move to image_similarity module and make into proper test
     #[test]
     #[serial]
@@ -0,0 +1,85 @@
+use std::path::Path;
+use crate::common;
+use crate::common::error::{Error, Result};
+use crate::{PantsuTag, PantsuTagType, Rating};
+use super::ModelTaggerConfig;
+
+// label categories of the danbooru tag groups used by WD14 models, general tags are category 0
+const CATEGORY_ARTIST: &str = "1";
+const CATEGORY_COPYRIGHT: &str = "3";
+const CATEGORY_CHARACTER: &str = "4";
+const CATEGORY_RATING: &str = "9";
+
+// one label per model output, in the order of the outputs
+pub(crate) struct ModelLabels {
+    labels: Vec<(String, PantsuTagType)>,
+}
+
+impl ModelLabels {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::FileNotFound(e, common::get_path(path)))?;
+        Self::parse(&content)
+            .map_err(|e| Error::InvalidModelLabels(common::get_path(path), e))
+    }
+
+    // expects the csv header tag_id,name,category,count. Unknown categories count as general tags
+    pub(crate) fn parse(content: &str) -> std::result::Result<Self, String> {
+        let labels = content.lines()
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (_, rest) = line.split_once(',').ok_or_else(|| format!("invalid line '{}'", line))?;
+                let (name, category) = rest.rsplitn(3, ',').collect::<Vec<_>>()
+                    .get(1..3)
+                    .map(|columns| (columns[1], columns[0]))
+                    .ok_or_else(|| format!("invalid line '{}'", line))?;
+                let tag_type = match category {
+                    CATEGORY_ARTIST => PantsuTagType::Artist,
+                    CATEGORY_COPYRIGHT => PantsuTagType::Source,
+                    CATEGORY_CHARACTER => PantsuTagType::Character,
+                    CATEGORY_RATING => PantsuTagType::Rating,
+                    _ => PantsuTagType::General,
+                };
+                Ok((name.to_string(), tag_type))
+            })
+            .collect::<std::result::Result<Vec<_>, String>>()?;
+        if labels.is_empty() {
+            return Err(String::from("no labels found"));
+        }
+        Ok(ModelLabels { labels })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    // returns the tags above their threshold with their confidence, highest first.
+    // Only the most confident rating is kept, regardless of the thresholds
+    pub(crate) fn to_tags(&self, confidences: &[f32], config: &ModelTaggerConfig) -> Vec<(PantsuTag, f32)> {
+        let mut rating: Option<(&str, f32)> = None;
+        let mut tags = Vec::new();
+        for ((name, tag_type), &confidence) in self.labels.iter().zip(confidences) {
+            let threshold = match tag_type {
+                PantsuTagType::Rating => {
+                    if !matches!(rating, Some((_, best)) if best >= confidence) {
+                        rating = Some((name, confidence));
+                    }
+                    continue;
+                },
+                PantsuTagType::Character => config.character_threshold,
+                _ => config.general_threshold,
+            };
+            if confidence >= threshold {
+                tags.push((PantsuTag::new(name.replace('_', " "), *tag_type), confidence));
+            }
+        }
+        if let Some((name, confidence)) = rating {
+            if let Ok(rating) = Rating::deserialize(name) {
+                tags.push((rating.to_tag(), confidence));
+            }
+        }
+        tags.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        tags
+    }
+}
@@ -0,0 +1,42 @@
+mod tagger_config;
+#[cfg(feature = "ai-tagger")]
+mod labels;
+#[cfg(feature = "ai-tagger")]
+mod onnx_tagger;
+
+pub use tagger_config::ModelTaggerConfig;
+#[cfg(feature = "ai-tagger")]
+pub use onnx_tagger::ModelTagger;
+
+#[cfg(all(test, feature = "ai-tagger"))]
+mod tests {
+    use crate::{PantsuTag, PantsuTagType};
+    use super::labels::ModelLabels;
+    use super::ModelTaggerConfig;
+
+    const LABELS: &str = "tag_id,name,category,count
+9999999,general,9,807691
+9999998,sensitive,9,2392663
+9999997,questionable,9,735952
+9999996,explicit,9,592776
+470575,1girl,0,4226037
+212816,solo,0,3395587
+1709,long_hair,0,2880484
+1300281,hatsune_miku,4,132217";
+
+    #[test]
+    fn model_labels_to_tags() {
+        let labels = ModelLabels::parse(LABELS).unwrap();
+        assert_eq!(labels.len(), 8);
+        let config = ModelTaggerConfig::default();
+        let tags = labels.to_tags(&[0.1, 0.6, 0.3, 0.05, 0.98, 0.2, 0.4, 0.8], &config);
+        let tags = tags.into_iter().map(|(tag, _)| tag).collect::<Vec<PantsuTag>>();
+        assert_eq!(tags, vec![
+            PantsuTag::new(String::from("1girl"), PantsuTagType::General),
+            PantsuTag::new(String::from("Sensitive"), PantsuTagType::Rating),
+            PantsuTag::new(String::from("long hair"), PantsuTagType::General),
+        ]);
+        assert!(ModelLabels::parse("tag_id,name,category,count\n").is_err());
+        assert!(ModelLabels::parse("tag_id,name,category,count\n1,broken").is_err());
+    }
+}
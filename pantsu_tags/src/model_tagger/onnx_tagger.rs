@@ -0,0 +1,74 @@
+use std::path::Path;
+use image::{imageops, Rgb, RgbImage};
+use tract_onnx::prelude::*;
+use crate::common;
+use crate::common::error::{Error, Result};
+use crate::PantsuTag;
+use super::labels::ModelLabels;
+use super::ModelTaggerConfig;
+
+/// Tags images with a WD14-style onnx model. The model takes square BGR images with values
+/// from 0 to 255 in NHWC layout and outputs one confidence per label
+pub struct ModelTagger {
+    model: TypedRunnableModel<TypedModel>,
+    labels: ModelLabels,
+    config: ModelTaggerConfig,
+}
+
+impl ModelTagger {
+    pub fn new(config: &ModelTaggerConfig) -> Result<Self> {
+        let (model_path, labels_path) = match (&config.model_path, config.get_labels_path()) {
+            (Some(model_path), Some(labels_path)) => (model_path, labels_path),
+            _ => return Err(Error::NoTaggerModel),
+        };
+        let labels = ModelLabels::load(&labels_path)?;
+        let size = config.input_size as usize;
+        let model = tract_onnx::onnx()
+            .model_for_path(model_path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, size, size, 3]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| Error::ModelLoadError(common::get_path(model_path), e.to_string()))?;
+        Ok(ModelTagger {
+            model,
+            labels,
+            config: config.clone(),
+        })
+    }
+
+    /// Returns the predicted tags with their confidence, highest first
+    pub fn tag_image(&self, image_path: &Path) -> Result<Vec<(PantsuTag, f32)>> {
+        let input = prepare_image(image_path, self.config.input_size)?;
+        let outputs = self.model.run(tvec!(input.into()))
+            .map_err(|e| Error::ModelInferenceError(e.to_string()))?;
+        let confidences = outputs[0].to_array_view::<f32>()
+            .map_err(|e| Error::ModelInferenceError(e.to_string()))?;
+        if confidences.len() != self.labels.len() {
+            return Err(Error::ModelInferenceError(format!("model has {} outputs, but {} labels are loaded", confidences.len(), self.labels.len())));
+        }
+        let confidences = confidences.iter().copied().collect::<Vec<f32>>();
+        Ok(self.labels.to_tags(&confidences, &self.config))
+    }
+}
+
+// pads the image to a white square before resizing, so the aspect ratio stays intact
+fn prepare_image(path: &Path, size: u32) -> Result<Tensor> {
+    let image = image::open(path)
+        .map_err(|_| Error::ImageLoadError(common::get_path(path)))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let side = width.max(height);
+    let (offset_x, offset_y) = ((side - width) / 2, (side - height) / 2);
+    let mut square = RgbImage::from_pixel(side, side, Rgb([255, 255, 255]));
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let alpha = pixel[3] as f32 / 255.0;
+        let blend = |channel: u8| (channel as f32 * alpha + 255.0 * (1.0 - alpha)).round() as u8;
+        square.put_pixel(x + offset_x, y + offset_y, Rgb([blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]));
+    }
+    let resized = imageops::resize(&square, size, size, imageops::FilterType::CatmullRom);
+    let size = size as usize;
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn((1, size, size, 3), |(_, y, x, c)| {
+        resized.get_pixel(x as u32, y as u32)[2 - c] as f32
+    }).into();
+    Ok(input)
+}
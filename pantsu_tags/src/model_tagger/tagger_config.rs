@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use serde_derive::{Deserialize, Serialize};
+
+const INPUT_SIZE: u32 = 448;
+const GENERAL_THRESHOLD: f32 = 0.35;
+const CHARACTER_THRESHOLD: f32 = 0.85;
+const LABELS_FILENAME: &str = "selected_tags.csv";
+
+/// Settings of the local tagger, which runs a WD14-style onnx model on the cpu
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModelTaggerConfig {
+    pub model_path: Option<PathBuf>,
+    // csv with the columns tag_id,name,category,count. None uses selected_tags.csv next to the model
+    pub labels_path: Option<PathBuf>,
+    // width and height of the square model input
+    pub input_size: u32,
+    // predictions below these confidences are dropped
+    pub general_threshold: f32,
+    pub character_threshold: f32,
+}
+
+impl ModelTaggerConfig {
+    pub fn get_labels_path(&self) -> Option<PathBuf> {
+        match (&self.labels_path, &self.model_path) {
+            (Some(labels_path), _) => Some(labels_path.clone()),
+            (None, Some(model_path)) => Some(model_path.with_file_name(LABELS_FILENAME)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Default for ModelTaggerConfig {
+    fn default() -> Self {
+        ModelTaggerConfig {
+            model_path: None,
+            labels_path: None,
+            input_size: INPUT_SIZE,
+            general_threshold: GENERAL_THRESHOLD,
+            character_threshold: CHARACTER_THRESHOLD,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use reqwest::{StatusCode, Url};
+use serde_derive::Deserialize;
+use crate::common::error::{Error, Result};
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+use crate::common::rating::Rating;
+
+use super::{BooruConfig, SauceLiveness, SauceMatch, SaucePost, net};
+use super::net::HttpClient;
+
+#[derive(Deserialize)]
+struct Post {
+    tag_string_artist: String,
+    tag_string_character: String,
+    tag_string_copyright: String,
+    tag_string_general: String,
+    tag_string_meta: String,
+    rating: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PostInfo {
+    id: u64,
+    image_width: i32,
+    image_height: i32,
+}
+
+#[derive(Deserialize)]
+struct PostPreview {
+    // missing for posts that are restricted to gold accounts
+    preview_file_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PostStatus {
+    #[serde(default)]
+    is_deleted: bool,
+}
+
+#[derive(Deserialize)]
+struct PostFile {
+    // missing for posts that are restricted to gold accounts
+    file_url: Option<String>,
+    image_width: i32,
+    image_height: i32,
+    #[serde(default)]
+    source: String,
+}
+
+// Returns None if there is no post with this md5 on danbooru
+pub async fn find_sauce_by_md5(client: &HttpClient, md5: &str, booru: &BooruConfig) -> Result<Option<SauceMatch>> {
+    let mut post_url = net::booru_url(&booru.address, booru)?;
+    post_url.set_path("/posts.json");
+    post_url.query_pairs_mut().append_pair("md5", md5);
+    let resp = client.get(post_url).await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    net::check_status(resp.status())?;
+    let post_json = resp.text().await?;
+    extract_md5_sauce(&post_json, booru).map(Some)
+}
+
+pub(crate) fn extract_md5_sauce(post_json: &str, booru: &BooruConfig) -> Result<SauceMatch> {
+    let post: PostInfo = serde_json::from_str(post_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    let mut link = net::booru_url(&booru.address, booru)?;
+    link.set_path(&format!("/posts/{}", post.id));
+    link.set_query(None);
+    Ok(SauceMatch {
+        link: link.to_string(),
+        similarity: 100, // md5 matches are exact
+        resolution: (post.image_width, post.image_height),
+        provider: String::new(),
+    })
+}
+
+pub async fn find_tags_danbooru(client: &HttpClient, url: &str, booru: &BooruConfig) -> Result<Vec<PantsuTag>> {
+    let resp = client.get(post_api_url(url, booru)?).await?;
+    net::check_status(resp.status())?;
+    let post_json = resp.text().await?;
+    extract_tags(&post_json)
+}
+
+pub async fn find_post_danbooru(client: &HttpClient, url: &str, booru: &BooruConfig) -> Result<SaucePost> {
+    let resp = client.get(post_api_url(url, booru)?).await?;
+    net::check_status(resp.status())?;
+    let post_json = resp.text().await?;
+    extract_post(&post_json)
+}
+
+// the preview has to be on the booru or one of its cdn subdomains
+pub async fn find_thumbnail_url_danbooru(client: &HttpClient, url: &str, booru: &BooruConfig) -> Result<Url> {
+    let resp = client.get(post_api_url(url, booru)?).await?;
+    net::check_status(resp.status())?;
+    let post_json = resp.text().await?;
+    net::booru_url(&extract_thumbnail_link(&post_json)?, booru)
+}
+
+pub(crate) fn extract_thumbnail_link(post_json: &str) -> Result<String> {
+    let post: PostPreview = serde_json::from_str(post_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    post.preview_file_url.ok_or_else(|| Error::ApiParseError(String::from("post has no preview")))
+}
+
+// deleted posts are still served by the api, only flagged as deleted
+pub async fn check_liveness_danbooru(client: &HttpClient, url: &str, booru: &BooruConfig) -> Result<SauceLiveness> {
+    let resp = client.get(post_api_url(url, booru)?).await?;
+    net::check_status(resp.status())?;
+    let post_json = resp.text().await?;
+    extract_liveness(&post_json)
+}
+
+pub(crate) fn extract_liveness(post_json: &str) -> Result<SauceLiveness> {
+    let post: PostStatus = serde_json::from_str(post_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    match post.is_deleted {
+        true => Ok(SauceLiveness::Dead),
+        false => Ok(SauceLiveness::Alive),
+    }
+}
+
+pub(crate) fn extract_post(post_json: &str) -> Result<SaucePost> {
+    let post: PostFile = serde_json::from_str(post_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    Ok(SaucePost {
+        file_url: post.file_url,
+        resolution: (post.image_width, post.image_height),
+        source: Some(post.source).filter(|source| !source.trim().is_empty()),
+    })
+}
+
+// https://danbooru.donmai.us/posts/1234 -> https://danbooru.donmai.us/posts/1234.json
+// https://danbooru.donmai.us/posts?md5=abcd -> https://danbooru.donmai.us/posts.json?md5=abcd
+fn post_api_url(url: &str, booru: &BooruConfig) -> Result<Url> {
+    let mut post_url = net::booru_url(url, booru)?;
+    let segments = post_url.path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let md5 = post_url.query_pairs()
+        .find(|(key, _)| key == "md5")
+        .map(|(_, value)| value.to_string());
+    match (segments.as_slice(), md5) {
+        (["posts", id], _) if id.parse::<u64>().is_ok() => {
+            post_url.set_path(&format!("/posts/{}.json", id));
+            post_url.set_query(None);
+        },
+        (["posts"], Some(md5)) => {
+            post_url.set_path("/posts.json");
+            post_url.query_pairs_mut().clear().append_pair("md5", &md5);
+        },
+        _ => return Err(Error::BadUrl(url.to_string())),
+    }
+    Ok(post_url)
+}
+
+pub(crate) fn extract_tags(post_json: &str) -> Result<Vec<PantsuTag>> {
+    let post: Post = serde_json::from_str(post_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    let mut tags: Vec<PantsuTag> = Vec::new();
+    push_tags(&post.tag_string_artist, PantsuTagType::Artist, &mut tags);
+    push_tags(&post.tag_string_character, PantsuTagType::Character, &mut tags);
+    push_tags(&post.tag_string_copyright, PantsuTagType::Source, &mut tags);
+    push_tags(&post.tag_string_general, PantsuTagType::General, &mut tags);
+    push_tags(&post.tag_string_meta, PantsuTagType::General, &mut tags);
+    if let Some(rating) = post.rating.as_deref().and_then(rating_from_api) {
+        tags.push(rating.to_tag());
+    }
+
+    if tags.is_empty() {
+        return Err(Error::ApiParseError(String::from("post has no tags")));
+    }
+    Ok(tags)
+}
+
+// the api separates words with underscores, gelbooru tags are stored with spaces
+fn push_tags(tag_string: &str, tag_type: PantsuTagType, result: &mut Vec<PantsuTag>) {
+    result.extend(tag_string.split_whitespace()
+        .map(|name| PantsuTag::new(name.replace('_', " "), tag_type)));
+}
+
+fn rating_from_api(rating: &str) -> Option<Rating> {
+    match rating {
+        "g" => Some(Rating::General),
+        "s" => Some(Rating::Sensitive),
+        "q" => Some(Rating::Questionable),
+        "e" => Some(Rating::Explicit),
+        _ => None,
+    }
+}
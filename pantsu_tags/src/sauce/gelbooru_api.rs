@@ -0,0 +1,206 @@
+use reqwest::Url;
+use serde_derive::Deserialize;
+use crate::common::error::{Error, Result};
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+use crate::common::rating::Rating;
+
+use super::{BooruConfig, SauceMatch, SaucePost, net};
+use super::net::HttpClient;
+
+// tag type ids returned by the gelbooru tag api
+// the tag api returns only 100 tags by default, 1000 is the most it allows
+const MAX_TAGS_PER_REQUEST: usize = 1000;
+const TAG_TYPE_ARTIST: i32 = 1;
+const TAG_TYPE_COPYRIGHT: i32 = 3;
+const TAG_TYPE_CHARACTER: i32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GelbooruCredentials {
+    pub api_key: String,
+    pub user_id: String,
+}
+
+#[derive(Deserialize)]
+struct PostsResponse {
+    #[serde(default)]
+    post: Vec<Post>,
+}
+
+#[derive(Deserialize)]
+struct Post {
+    #[serde(default)]
+    id: u64,
+    #[serde(default)]
+    width: i32,
+    #[serde(default)]
+    height: i32,
+    #[serde(default)]
+    file_url: String,
+    #[serde(default)]
+    source: String,
+    tags: String,
+    rating: String,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    tag: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+    #[serde(rename = "type")]
+    tag_type: i32,
+}
+
+// If image was deleted on gelbooru, throws a PostDeleted error
+pub async fn find_tags_gelbooru_api(client: &HttpClient, url: &str, booru: &BooruConfig, credentials: Option<&GelbooruCredentials>) -> Result<Vec<PantsuTag>> {
+    let post_url = post_api_url(url, booru, credentials)?;
+    let resp = client.get(post_url).await?;
+    net::check_status(resp.status())?;
+    let posts_json = resp.text().await?;
+
+    let tag_names = extract_tag_names(&posts_json)?;
+    let resp = client.get(tag_api_url(url, &tag_names, booru, credentials)?).await?;
+    net::check_status(resp.status())?;
+    let tags_json = resp.text().await?;
+    extract_tags(&posts_json, &tags_json)
+}
+
+// If image was deleted on gelbooru, throws a PostDeleted error
+pub async fn find_post_gelbooru_api(client: &HttpClient, url: &str, booru: &BooruConfig, credentials: Option<&GelbooruCredentials>) -> Result<SaucePost> {
+    let resp = client.get(post_api_url(url, booru, credentials)?).await?;
+    net::check_status(resp.status())?;
+    let posts_json = resp.text().await?;
+    extract_post(&posts_json)
+}
+
+// Returns None if there is no post with this md5 on gelbooru
+pub async fn find_sauce_by_md5(client: &HttpClient, md5: &str, booru: &BooruConfig, credentials: Option<&GelbooruCredentials>) -> Result<Option<SauceMatch>> {
+    let post_url = api_url(net::booru_url(&booru.address, booru)?, "post", ("tags", format!("md5:{}", md5)), credentials);
+    let resp = client.get(post_url).await?;
+    net::check_status(resp.status())?;
+    let posts_json = resp.text().await?;
+    extract_md5_sauce(&posts_json, booru)
+}
+
+pub(crate) fn extract_md5_sauce(posts_json: &str, booru: &BooruConfig) -> Result<Option<SauceMatch>> {
+    let posts: PostsResponse = serde_json::from_str(posts_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    let post = match posts.post.into_iter().next() {
+        Some(post) => post,
+        None => return Ok(None),
+    };
+    let mut link = net::booru_url(&booru.address, booru)?;
+    link.set_path("/index.php");
+    link.set_query(Some(&format!("page=post&s=view&id={}", post.id)));
+    Ok(Some(SauceMatch {
+        link: link.to_string(),
+        similarity: 100, // md5 matches are exact
+        resolution: (post.width, post.height),
+        provider: String::new(),
+    }))
+}
+
+fn post_api_url(url: &str, booru: &BooruConfig, credentials: Option<&GelbooruCredentials>) -> Result<Url> {
+    let post_url = net::booru_url(url, booru)?;
+    let query = post_url.query_pairs()
+        .find_map(|(key, value)| match key.as_ref() {
+            "id" => Some(("id", value.to_string())),
+            "md5" => Some(("tags", format!("md5:{}", value))),
+            _ => None,
+        })
+        .ok_or_else(|| Error::BadUrl(url.to_string()))?;
+    Ok(api_url(post_url, "post", query, credentials))
+}
+
+pub(crate) fn tag_api_url(url: &str, tag_names: &[String], booru: &BooruConfig, credentials: Option<&GelbooruCredentials>) -> Result<Url> {
+    let tag_url = net::booru_url(url, booru)?;
+    let mut tag_url = api_url(tag_url, "tag", ("names", tag_names.join(" ")), credentials);
+    tag_url.query_pairs_mut().append_pair("limit", &tag_names.len().clamp(1, MAX_TAGS_PER_REQUEST).to_string());
+    Ok(tag_url)
+}
+
+fn api_url(mut url: Url, api: &str, query: (&str, String), credentials: Option<&GelbooruCredentials>) -> Url {
+    url.set_path("/index.php");
+    url.set_query(None);
+    {
+        let mut pairs = url.query_pairs_mut();
+        pairs.append_pair("page", "dapi")
+            .append_pair("s", api)
+            .append_pair("q", "index")
+            .append_pair("json", "1")
+            .append_pair(query.0, &query.1);
+        if let Some(credentials) = credentials {
+            pairs.append_pair("api_key", &credentials.api_key)
+                .append_pair("user_id", &credentials.user_id);
+        }
+    }
+    url
+}
+
+fn parse_post(posts_json: &str) -> Result<Post> {
+    let posts: PostsResponse = serde_json::from_str(posts_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+    posts.post.into_iter().next()
+        .ok_or(Error::PostDeleted)
+}
+
+pub(crate) fn extract_post(posts_json: &str) -> Result<SaucePost> {
+    let post = parse_post(posts_json)?;
+    Ok(SaucePost {
+        file_url: non_empty(post.file_url),
+        resolution: (post.width, post.height),
+        source: non_empty(post.source),
+    })
+}
+
+fn extract_tag_names(posts_json: &str) -> Result<Vec<String>> {
+    let post = parse_post(posts_json)?;
+    Ok(post.tags.split_whitespace().map(|t| t.to_string()).collect())
+}
+
+pub(crate) fn extract_tags(posts_json: &str, tags_json: &str) -> Result<Vec<PantsuTag>> {
+    let post = parse_post(posts_json)?;
+    let tag_types: TagsResponse = serde_json::from_str(tags_json)
+        .map_err(|e| Error::ApiParseError(e.to_string()))?;
+
+    let mut tags: Vec<PantsuTag> = post.tags.split_whitespace()
+        .map(|name| {
+            let tag_type = tag_types.tag.iter()
+                .find(|t| t.name == name)
+                .map(|t| tag_type_from_id(t.tag_type))
+                .unwrap_or(PantsuTagType::General);
+            PantsuTag::new(tag_name_from_api(name), tag_type)
+        })
+        .collect();
+    // the api returns lowercase ratings like "questionable"
+    if let Ok(rating) = Rating::deserialize(&post.rating) {
+        tags.push(rating.to_tag());
+    }
+    Ok(tags)
+}
+
+// the api returns empty strings for missing fields
+fn non_empty(value: String) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        _ => Some(value),
+    }
+}
+
+fn tag_type_from_id(id: i32) -> PantsuTagType {
+    match id {
+        TAG_TYPE_ARTIST => PantsuTagType::Artist,
+        TAG_TYPE_COPYRIGHT => PantsuTagType::Source,
+        TAG_TYPE_CHARACTER => PantsuTagType::Character,
+        _ => PantsuTagType::General, // metadata and deprecated tags are treated as general tags
+    }
+}
+
+// the api separates words with underscores, the html page (and thus existing tags) with spaces
+fn tag_name_from_api(name: &str) -> String {
+    name.replace('_', " ")
+}
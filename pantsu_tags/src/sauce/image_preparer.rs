@@ -79,7 +79,9 @@ pub async fn prepare_image(image_handle: &ImageHandle, lib: &Path) -> Result<Ima
 }
 
 fn compress_image(image_path: &Path, res_dir: &Path, factor_func: fn(u32, u32, u64) -> Factor) -> Result<ImagePrepared> {
-    let comp = Compressor::new(image_path, res_dir, factor_func);
+    let mut comp = Compressor::new(image_path, res_dir);
+    let (w, h) = image::image_dimensions(image_path).unwrap_or((0, 0));
+    comp.set_factor(factor_func(w, h, 0));
     let res_image = comp.compress_to_jpg()
         .or_else(|e| {
             compress_image_try_fix_error(image_path, res_dir, e)?;
@@ -6,12 +6,28 @@ use crate::error::{Error, Result};
 mod tag_finder;
 mod sauce_finder;
 mod image_preparer;
+mod danbooru_api;
+mod gelbooru_api;
 mod net;
+mod provider;
+mod sauce_config;
+mod response_cache;
+mod sauce_judge;
 
+use crate::common::image_source::{ImageSource, ImageSourceKind};
+use chrono::NaiveDateTime;
 use reqwest::Url;
-pub use sauce_finder::find_sauce;
+use serde_derive::{Deserialize, Serialize};
 pub use sauce_finder::get_thumbnails;
-pub use tag_finder::find_tags_gelbooru;
+pub(crate) use sauce_finder::download_original;
+pub use sauce_finder::{DanbooruMd5SauceProvider, GelbooruMd5SauceProvider, IqdbSauceProvider};
+pub use tag_finder::{DanbooruTagProvider, GelbooruTagProvider};
+pub use gelbooru_api::GelbooruCredentials;
+pub use provider::{Md5SauceProvider, SauceLiveness, SauceProvider, SauceProviders, TagProvider};
+pub use sauce_config::{BooruConfig, CacheConfig, HttpConfig, SauceConfig};
+pub use net::HttpClient;
+pub use sauce_judge::{SauceJudgePolicy, SauceJudgement};
+pub(crate) use sauce_judge::judge_sauce;
 
 pub fn url_from_str(url: &str) -> Result<Url> {
     Url::parse(url).or_else(|_| Err(Error::InvalidSauce(url.to_string())))
@@ -20,6 +36,7 @@ pub fn url_from_str(url: &str) -> Result<Url> {
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Sauce {
     Match(Url),
+    Unsure,         // candidates were found but need a review, see the sauce_candidates of the image
     NotExisting,
     NotChecked
 }
@@ -28,6 +45,7 @@ impl Sauce {
     pub fn get_type(&self) -> &str {
         match self {
             Sauce::Match(_) => EXISTING_FLAG,
+            Sauce::Unsure => UNSURE_FLAG,
             Sauce::NotChecked => NOT_CHECKED_FLAG,
             Sauce::NotExisting => NOT_EXISTING_FLAG,
         }
@@ -36,6 +54,7 @@ impl Sauce {
     pub fn get_value(&self) -> Option<&str> {
         match self {
             Sauce::Match(url) => Some(url.as_str()),
+            Sauce::Unsure => None,
             Sauce::NotChecked => None,
             Sauce::NotExisting => None,
         }
@@ -46,6 +65,7 @@ impl Display for Sauce {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", match self {
             Sauce::Match(v) => v.as_str(),
+            Sauce::Unsure => UNSURE_FLAG,
             Sauce::NotChecked => NOT_CHECKED_FLAG,
             Sauce::NotExisting => NOT_EXISTING_FLAG
         })
@@ -54,17 +74,68 @@ impl Display for Sauce {
 
 pub const EXISTING_FLAG: &str =
     "EXISTING";
+pub const UNSURE_FLAG: &str =
+    "UNSURE";
 pub const NOT_EXISTING_FLAG: &str =
     "NOT_EXISTING";
 
 pub const NOT_CHECKED_FLAG: &str =
     "NOT_CHECKED";
 
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Clone, Deserialize, Serialize)]
 pub struct SauceMatch {
     pub link: String,           // link can be invalid if image was deleted on gelbooru
     pub similarity: i32,
     pub resolution: (i32, i32),
+    #[serde(default)]
+    pub provider: String,       // name of the provider that found the match, set by SauceProviders
+}
+
+// details of a sauce post that are not tags
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct SaucePost {
+    pub file_url: Option<String>,   // the original file, None if the booru restricts it
+    pub resolution: (i32, i32),
+    pub source: Option<String>,     // where the uploader found the image, e.g. a pixiv or twitter post
+}
+
+impl SaucePost {
+    // the booru post itself and the source given on the post
+    pub fn get_image_sources(&self, sauce_link: &str) -> Vec<ImageSource> {
+        let mut sources = vec![ImageSource::new(sauce_link.to_string(), ImageSourceKind::BooruPost)];
+        if let Some(source) = &self.source {
+            sources.push(ImageSource::new(source.clone(), ImageSourceKind::of_original_source(source)));
+        }
+        sources
+    }
+}
+
+// a sauce match as stored in the database, all candidates of one lookup share the same date
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SauceCandidate {
+    pub link: String,
+    pub provider: String,
+    pub similarity: i32,
+    pub resolution: (i32, i32),
+    pub date_added: NaiveDateTime,
+}
+
+// a sauce lookup as stored in the database, with the policy its matches were judged by
+#[derive(Debug, PartialEq, Clone)]
+pub struct SauceLookup {
+    pub judge_policy: Option<SauceJudgePolicy>,
+    pub date_added: NaiveDateTime,
+}
+
+impl SauceCandidate {
+    pub fn to_sauce_match(&self) -> SauceMatch {
+        SauceMatch {
+            link: self.link.clone(),
+            similarity: self.similarity,
+            resolution: self.resolution,
+            provider: self.provider.clone(),
+        }
+    }
 }
 
 impl Ord for SauceMatch {
@@ -91,11 +162,14 @@ impl PartialEq for SauceMatch {
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
-    use std::path::PathBuf;
-    use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+    use reqwest::StatusCode;
+    use std::path::{Path, PathBuf};
+    use futures::future::BoxFuture;
+    use crate::common::pantsu_tag::{PantsuTag, PantsuTagAuthor, PantsuTagType};
+    use crate::sauce::{SauceLiveness, TagProvider};
     use crate::file_handler::hash::{self};
-    use crate::sauce::{sauce_finder, tag_finder};
-    use crate::{SauceMatch};
+    use crate::sauce::{danbooru_api, gelbooru_api, net, sauce_finder, tag_finder};
+    use crate::{BooruConfig, CacheConfig, Error, HttpClient, HttpConfig, ImageHandle, ImageSource, ImageSourceKind, Result, SauceConfig, SauceMatch, SaucePost, SauceProvider, SauceProviders};
 
     fn prepare_image(image_link: &str) -> PathBuf {
         let image_name = image_link.rsplit('/').next().unwrap();
@@ -120,7 +194,7 @@ mod tests {
         std::fs::copy(path, image.get_filename()).unwrap();
         //let image = ImageHandle::new(path.file_name().unwrap().to_str().unwrap().to_string(), Sauce::NotChecked, (0, 0));
 
-        let sauces = sauce_finder::find_sauce(&image, &PathBuf::from(".")).await.unwrap();
+        let sauces = sauce_finder::IqdbSauceProvider::default().find_sauce(&image, &PathBuf::from(".")).await.unwrap();
         std::fs::remove_file(image.get_filename()).unwrap();
         assert_eq!(sauces[0].link, sauce_link);
         assert_eq!(sauces[0].similarity, 95);
@@ -130,13 +204,13 @@ mod tests {
     #[tokio::test]
     async fn find_tag() {
         let url = "http://gelbooru.com/index.php?page=post&s=list&md5=4f76b8d52983af1d28b1bf8d830d684e";
-        let tags = tag_finder::find_tags_gelbooru(url).await.unwrap();
+        let tags = tag_finder::find_tags_gelbooru(&HttpClient::default(), url, &SauceConfig::default().gelbooru).await.unwrap();
         assert!(tags.iter().any(|tag| tag.tag_name.eq("loli") && matches!(tag.tag_type, PantsuTagType::General)));
         assert!(tags.iter().any(|tag| tag.tag_name.eq("stuffed dinosaur") && matches!(tag.tag_type, PantsuTagType::General)));
         assert!(tags.iter().any(|tag| tag.tag_name.eq("ichihaya") && matches!(tag.tag_type, PantsuTagType::Artist)));
         assert!(tags.iter().any(|tag| tag.tag_name.eq("awano iroha") && matches!(tag.tag_type, PantsuTagType::Character)));
         assert!(tags.iter().any(|tag| tag.tag_name.eq("original") && matches!(tag.tag_type, PantsuTagType::Source)));
-        assert!(tags.iter().any(|tag| tag.tag_name.eq("Questionable") && matches!(tag.tag_type, PantsuTagType::Rating)));
+        assert!(tags.iter().any(|tag| tag.tag_name.eq("questionable") && matches!(tag.tag_type, PantsuTagType::Rating)));
         assert!(!tags.iter().any(|tag| tag.tag_name.eq("large breasts") && matches!(tag.tag_type, PantsuTagType::Source)));
     }
 
@@ -151,7 +225,7 @@ mod tests {
         ];
         let mut tags: Vec<Vec<PantsuTag>> = Vec::new();
         for link in links {
-            tags.push(tag_finder::find_tags_gelbooru(link).await.unwrap());
+            tags.push(tag_finder::find_tags_gelbooru(&HttpClient::default(), link, &SauceConfig::default().gelbooru).await.unwrap());
         }
         for tag in tags {
             assert!(tag.iter().any(|t| t.tag_name.eq("original") && matches!(t.tag_type, PantsuTagType::Source)))
@@ -161,19 +235,286 @@ mod tests {
     #[tokio::test]
     async fn find_tags_rating() {
         let links: Vec<(&str, &str)> = vec![
-            ("https://gelbooru.com/index.php?page=post&s=view&id=6250367&tags=rurudo", "Sensitive"),
-            ("https://gelbooru.com/index.php?page=post&s=view&id=5558687&tags=rurudo", "Questionable"),
-            ("https://gelbooru.com/index.php?page=post&s=view&id=5591747&tags=rurudo", "Explicit"),
+            ("https://gelbooru.com/index.php?page=post&s=view&id=6250367&tags=rurudo", "sensitive"),
+            ("https://gelbooru.com/index.php?page=post&s=view&id=5558687&tags=rurudo", "questionable"),
+            ("https://gelbooru.com/index.php?page=post&s=view&id=5591747&tags=rurudo", "explicit"),
         ];
         let mut tags: Vec<(Vec<PantsuTag>, &str)> = Vec::new();
         for link in links {
-            tags.push((tag_finder::find_tags_gelbooru(link.0).await.unwrap(), link.1));
+            tags.push((tag_finder::find_tags_gelbooru(&HttpClient::default(), link.0, &SauceConfig::default().gelbooru).await.unwrap(), link.1));
         }
         for tag in tags {
             assert!(tag.0.iter().any(|t| t.tag_name.eq(tag.1) && matches!(t.tag_type, PantsuTagType::Rating)))
         }
     }
 
+    struct FixedSauceProvider(Vec<(&'static str, i32)>);
+
+    impl SauceProvider for FixedSauceProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn find_sauce<'a>(&'a self, _image: &'a ImageHandle, _lib: &'a Path) -> BoxFuture<'a, Result<Vec<SauceMatch>>> {
+            Box::pin(async move {
+                Ok(self.0.iter()
+                    .map(|(link, similarity)| SauceMatch { link: link.to_string(), similarity: *similarity, resolution: (10, 10), provider: String::new() })
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn merge_provider_sauces() {
+        let providers = SauceProviders::new()
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("a", 50), ("b", 80)])))
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("b", 95), ("c", 60)])));
+        let image = ImageHandle::new(String::from("1b64e362cdf968d9-c1fc07e23e05e2fc0be39ce8cc88f8044fcf.jpg")).unwrap();
+        let sauces = crate::get_image_sauces(&providers, Path::new("."), &image).await.unwrap();
+        let sauces = sauces.iter().map(|s| (s.link.as_str(), s.similarity)).collect::<Vec<_>>();
+        assert_eq!(sauces, vec![("b", 95), ("c", 60), ("a", 50)]);
+        assert!(matches!(providers.find_tags("https://yande.re/post/show/1").await, Err(Error::UnsupportedSauce(_))));
+    }
+
+    #[tokio::test]
+    async fn cached_provider_sauces() {
+        let cache_dir = std::env::temp_dir().join("pantsu-tags-test-cache");
+        let cache_config = CacheConfig { directory: Some(cache_dir.clone()), ttl_hours: 1 };
+        let image = ImageHandle::new(String::from("1b64e362cdf968d9-c1fc07e23e05e2fc0be39ce8cc88f8044fcf.jpg")).unwrap();
+        let providers = SauceProviders::new()
+            .with_cache(&cache_config)
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("a", 50)])));
+        providers.clear_cache().unwrap();
+        assert_eq!(providers.find_sauce(&image, Path::new(".")).await.unwrap()[0].link, "a");
+
+        // a lookup with other providers now returns the cached sauces
+        let providers = SauceProviders::new()
+            .with_cache(&cache_config)
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("b", 80)])));
+        assert_eq!(providers.find_sauce(&image, Path::new(".")).await.unwrap()[0].link, "a");
+        assert!(providers.find_sauce_cached(&image, Path::new(".")).await.unwrap().1);
+        assert_eq!(providers.clear_cache().unwrap(), 1);
+        assert_eq!(providers.find_sauce(&image, Path::new(".")).await.unwrap()[0].link, "b");
+
+        // a refresh bypasses the cached sauces and replaces them
+        let refreshed = SauceProviders::new()
+            .with_cache(&cache_config)
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("d", 70)])));
+        assert_eq!(refreshed.refresh_sauce(&image, Path::new(".")).await.unwrap()[0].link, "d");
+        assert_eq!(providers.find_sauce_cached(&image, Path::new(".")).await.unwrap().0[0].link, "d");
+
+        let expired = SauceProviders::new()
+            .with_cache(&CacheConfig { directory: Some(cache_dir), ttl_hours: 0 })
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("c", 60)])));
+        assert_eq!(expired.find_sauce(&image, Path::new(".")).await.unwrap()[0].link, "c");
+        expired.clear_cache().unwrap();
+    }
+
+    struct FailingSauceProvider;
+
+    impl SauceProvider for FailingSauceProvider {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn find_sauce<'a>(&'a self, _image: &'a ImageHandle, _lib: &'a Path) -> BoxFuture<'a, Result<Vec<SauceMatch>>> {
+            Box::pin(async move { Err(Error::ApiParseError(String::from("provider unavailable"))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn uncached_incomplete_sauces() {
+        let cache_config = CacheConfig { directory: Some(std::env::temp_dir().join("pantsu-tags-test-cache-incomplete")), ttl_hours: 1 };
+        let image = ImageHandle::new(String::from("1b64e362cdf968d9-c1fc07e23e05e2fc0be39ce8cc88f8044fcf.jpg")).unwrap();
+        let partial = SauceProviders::new()
+            .with_cache(&cache_config)
+            .with_sauce_provider(Box::new(FixedSauceProvider(vec![("a", 50)])))
+            .with_sauce_provider(Box::new(FailingSauceProvider));
+        partial.clear_cache().unwrap();
+        assert_eq!(partial.find_sauce(&image, Path::new(".")).await.unwrap()[0].link, "a");
+
+        let empty = SauceProviders::new()
+            .with_cache(&cache_config)
+            .with_sauce_provider(Box::new(FixedSauceProvider(Vec::new())));
+        assert!(empty.find_sauce(&image, Path::new(".")).await.unwrap().is_empty());
+        assert_eq!(empty.clear_cache().unwrap(), 0);
+    }
+
+    struct FailingTagProvider(fn() -> Error);
+
+    impl TagProvider for FailingTagProvider {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn tag_author(&self) -> PantsuTagAuthor {
+            PantsuTagAuthor::Gelbooru
+        }
+
+        fn supports(&self, _link: &str) -> bool {
+            true
+        }
+
+        fn find_tags<'a>(&'a self, _link: &'a str) -> BoxFuture<'a, Result<Vec<PantsuTag>>> {
+            Box::pin(async move { Err((self.0)()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn sauce_liveness() {
+        let link = "https://gelbooru.com/index.php?page=post&s=view&id=6261499";
+        let liveness = |error| async move { FailingTagProvider(error).check_liveness(link).await };
+        assert!(matches!(liveness(|| Error::PostDeleted).await, Ok(SauceLiveness::Dead)));
+        assert!(matches!(liveness(|| Error::BadResponseStatus(StatusCode::NOT_FOUND)).await, Ok(SauceLiveness::Dead)));
+        assert!(matches!(liveness(|| Error::BadResponseStatus(StatusCode::GONE)).await, Ok(SauceLiveness::Dead)));
+        assert!(matches!(liveness(|| Error::ApiParseError(String::new())).await, Err(Error::ApiParseError(_))));
+        assert!(matches!(liveness(|| Error::HtmlParseError).await, Err(Error::HtmlParseError)));
+        assert!(liveness(|| Error::BadResponseStatus(StatusCode::SERVICE_UNAVAILABLE)).await.is_err());
+
+        assert_eq!(danbooru_api::extract_liveness(r#"{"id":5012345,"is_deleted":true}"#).unwrap(), SauceLiveness::Dead);
+        assert_eq!(danbooru_api::extract_liveness(r#"{"id":5012345,"is_deleted":false}"#).unwrap(), SauceLiveness::Alive);
+        assert!(matches!(danbooru_api::extract_liveness("<html></html>"), Err(Error::ApiParseError(_))));
+    }
+
+    #[test]
+    fn gelbooru_api_tags() {
+        let posts_json = r#"{"@attributes":{"limit":100,"offset":0,"count":1},"post":[{"id":6261499,"md5":"4f76b8d52983af1d28b1bf8d830d684e","rating":"questionable","tags":"awano_iroha ichihaya original stuffed_dinosaur tagme"}]}"#;
+        let tags_json = r#"{"@attributes":{"limit":100,"offset":0,"count":4},"tag":[{"id":1,"name":"awano_iroha","count":12,"type":4,"ambiguous":0},{"id":2,"name":"ichihaya","count":300,"type":1,"ambiguous":0},{"id":3,"name":"original","count":9000,"type":3,"ambiguous":0},{"id":4,"name":"stuffed_dinosaur","count":80,"type":0,"ambiguous":0}]}"#;
+        let tags = gelbooru_api::extract_tags(posts_json, tags_json).unwrap();
+        assert!(tags.contains(&PantsuTag::new(String::from("awano iroha"), PantsuTagType::Character)));
+        assert!(tags.contains(&PantsuTag::new(String::from("ichihaya"), PantsuTagType::Artist)));
+        assert!(tags.contains(&PantsuTag::new(String::from("original"), PantsuTagType::Source)));
+        assert!(tags.contains(&PantsuTag::new(String::from("stuffed dinosaur"), PantsuTagType::General)));
+        assert!(tags.contains(&PantsuTag::new(String::from("tagme"), PantsuTagType::General)));
+        assert!(tags.contains(&PantsuTag::new(String::from("Questionable"), PantsuTagType::Rating)));
+        assert!(matches!(gelbooru_api::extract_tags(r#"{"@attributes":{"count":0}}"#, tags_json), Err(Error::PostDeleted)));
+
+        let tag_names = vec![String::from("awano_iroha"), String::from("original")];
+        let tag_url = gelbooru_api::tag_api_url("https://gelbooru.com/index.php?page=post&s=view&id=6261499", &tag_names, &SauceConfig::default().gelbooru, None).unwrap();
+        assert!(tag_url.query_pairs().any(|(key, value)| key == "limit" && value == "2"));
+        assert!(tag_finder::can_fall_back_to_html(&Error::ApiParseError(String::new())));
+        assert!(tag_finder::can_fall_back_to_html(&Error::BadResponseStatus(StatusCode::UNAUTHORIZED)));
+        assert!(!tag_finder::can_fall_back_to_html(&Error::BadResponseStatus(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!tag_finder::can_fall_back_to_html(&Error::BadResponseStatus(StatusCode::BAD_GATEWAY)));
+    }
+
+    #[test]
+    fn danbooru_api_tags() {
+        let post_json = r#"{"id":5012345,"md5":"4f76b8d52983af1d28b1bf8d830d684e","rating":"q","tag_string_artist":"ichihaya","tag_string_character":"awano_iroha","tag_string_copyright":"original","tag_string_general":"1girl stuffed_dinosaur","tag_string_meta":"highres"}"#;
+        let tags = danbooru_api::extract_tags(post_json).unwrap();
+        assert!(tags.contains(&PantsuTag::new(String::from("ichihaya"), PantsuTagType::Artist)));
+        assert!(tags.contains(&PantsuTag::new(String::from("awano iroha"), PantsuTagType::Character)));
+        assert!(tags.contains(&PantsuTag::new(String::from("original"), PantsuTagType::Source)));
+        assert!(tags.contains(&PantsuTag::new(String::from("stuffed dinosaur"), PantsuTagType::General)));
+        assert!(tags.contains(&PantsuTag::new(String::from("highres"), PantsuTagType::General)));
+        assert!(tags.contains(&PantsuTag::new(String::from("Questionable"), PantsuTagType::Rating)));
+
+        let providers = SauceProviders::default();
+        assert_eq!(providers.tag_author("https://danbooru.donmai.us/posts/5012345").unwrap(), PantsuTagAuthor::Danbooru);
+        assert_eq!(providers.tag_author("https://gelbooru.com/index.php?page=post&s=view&id=6261499").unwrap(), PantsuTagAuthor::Gelbooru);
+    }
+
+    #[tokio::test]
+    async fn thumbnails_per_sauce() {
+        let sauce = |link: &str| SauceMatch { link: link.to_string(), similarity: 80, resolution: (10, 10), provider: String::new() };
+        let sauces = vec![sauce("https://yande.re/post/show/1"), sauce("https://konachan.com/post/show/2")];
+        let thumbnails = crate::get_thumbnails(&SauceProviders::default(), &sauces).await;
+        assert_eq!(thumbnails.len(), 2);
+        assert!(thumbnails.iter().all(|thumbnail| matches!(thumbnail, Err(Error::UnsupportedSauce(_)))));
+
+        let post_json = r#"{"id":5012345,"preview_file_url":"https://cdn.donmai.us/preview/4f/76/4f76b8d52983af1d28b1bf8d830d684e.jpg"}"#;
+        let link = danbooru_api::extract_thumbnail_link(post_json).unwrap();
+        assert!(net::booru_url(&link, &SauceConfig::default().danbooru).is_ok());
+        assert!(matches!(danbooru_api::extract_thumbnail_link(r#"{"id":5012345}"#), Err(Error::ApiParseError(_))));
+        let file_url = net::parse_url("https://cdn.donmai.us/preview/4f/76/4f76b8d5.jpg?download=1").unwrap();
+        assert_eq!(net::url_file_name(&file_url), "4f76b8d5.jpg");
+    }
+
+    #[test]
+    fn original_file_on_sauce_booru() {
+        let providers = SauceProviders::default();
+        let post = |file_url: &str| SaucePost { file_url: Some(file_url.to_string()), resolution: (10, 10), source: None };
+        let link = "https://danbooru.donmai.us/posts/5012345";
+        let file_url = providers.original_file_url(link, &post("https://cdn.donmai.us/original/4f/76/4f76b8d5.png?download=1")).unwrap();
+        assert_eq!(net::url_file_name(&file_url), "4f76b8d5.png");
+        assert!(matches!(providers.original_file_url(link, &post("https://evil.org/4f76b8d5.png")), Err(Error::BadUrl(_))));
+        assert!(matches!(providers.original_file_url(link, &post("https://img3.gelbooru.com/images/4f76b8d5.png")), Err(Error::BadUrl(_))));
+        assert!(providers.original_file_url("https://gelbooru.com/index.php?page=post&s=view&id=1", &post("https://img3.gelbooru.com/images/4f76b8d5.png")).is_ok());
+        assert!(matches!(providers.original_file_url("https://yande.re/post/show/1", &post("https://files.yande.re/image/4f76b8d5.png")), Err(Error::UnsupportedSauce(_))));
+        let restricted = SaucePost { file_url: None, resolution: (10, 10), source: None };
+        assert!(matches!(providers.original_file_url(link, &restricted), Err(Error::ApiParseError(_))));
+    }
+
+    #[test]
+    fn md5_sauce_lookup() {
+        let config = SauceConfig::default();
+        let posts_json = r#"{"@attributes":{"limit":100,"offset":0,"count":1},"post":[{"id":6261499,"width":533,"height":745,"md5":"4f76b8d52983af1d28b1bf8d830d684e","rating":"questionable","tags":"original"}]}"#;
+        let sauce = gelbooru_api::extract_md5_sauce(posts_json, &config.gelbooru).unwrap().unwrap();
+        assert_eq!(sauce.link, "https://gelbooru.com/index.php?page=post&s=view&id=6261499");
+        assert_eq!(sauce.similarity, 100);
+        assert_eq!(sauce.resolution, (533, 745));
+        assert!(gelbooru_api::extract_md5_sauce(r#"{"@attributes":{"limit":100,"offset":0,"count":0}}"#, &config.gelbooru).unwrap().is_none());
+
+        let post_json = r#"{"id":5012345,"md5":"4f76b8d52983af1d28b1bf8d830d684e","image_width":1066,"image_height":1490}"#;
+        let sauce = danbooru_api::extract_md5_sauce(post_json, &config.danbooru).unwrap();
+        assert_eq!(sauce.link, "https://danbooru.donmai.us/posts/5012345");
+        assert_eq!(sauce.similarity, 100);
+        assert_eq!(sauce.resolution, (1066, 1490));
+    }
+
+    #[test]
+    fn sauce_post_original() {
+        let posts_json = r#"{"@attributes":{"limit":100,"offset":0,"count":1},"post":[{"id":6261499,"width":2132,"height":2980,"file_url":"https://img3.gelbooru.com/images/4f/76/4f76b8d52983af1d28b1bf8d830d684e.png","rating":"questionable","tags":"original"}]}"#;
+        let post = gelbooru_api::extract_post(posts_json).unwrap();
+        assert_eq!(post.file_url.as_deref(), Some("https://img3.gelbooru.com/images/4f/76/4f76b8d52983af1d28b1bf8d830d684e.png"));
+        assert_eq!(post.resolution, (2132, 2980));
+        assert_eq!(post.source, None);
+        assert!(matches!(gelbooru_api::extract_post(r#"{"@attributes":{"count":0}}"#), Err(Error::PostDeleted)));
+
+        let post_json = r#"{"id":5012345,"image_width":1066,"image_height":1490,"file_url":"https://cdn.donmai.us/original/4f/76/4f76b8d52983af1d28b1bf8d830d684e.jpg"}"#;
+        let post = danbooru_api::extract_post(post_json).unwrap();
+        assert_eq!(post.file_url.as_deref(), Some("https://cdn.donmai.us/original/4f/76/4f76b8d52983af1d28b1bf8d830d684e.jpg"));
+        assert_eq!(post.resolution, (1066, 1490));
+        let restricted_post = danbooru_api::extract_post(r#"{"id":5012345,"image_width":1066,"image_height":1490,"source":"https://www.pixiv.net/artworks/12345"}"#).unwrap();
+        assert_eq!(restricted_post.file_url, None);
+        assert_eq!(restricted_post.source.as_deref(), Some("https://www.pixiv.net/artworks/12345"));
+    }
+
+    #[test]
+    fn sauce_post_sources() {
+        let posts_json = r#"{"@attributes":{"limit":100,"offset":0,"count":1},"post":[{"id":6261499,"width":533,"height":745,"source":"https://twitter.com/ichihaya/status/1234","rating":"questionable","tags":"original"}]}"#;
+        let link = "https://gelbooru.com/index.php?page=post&s=view&id=6261499";
+        let sources = gelbooru_api::extract_post(posts_json).unwrap().get_image_sources(link);
+        assert_eq!(sources, vec![
+            ImageSource::new(link.to_string(), ImageSourceKind::BooruPost),
+            ImageSource::new(String::from("https://twitter.com/ichihaya/status/1234"), ImageSourceKind::Original),
+        ]);
+        assert_eq!(ImageSourceKind::of_original_source("https://www.pixiv.net/users/1234"), ImageSourceKind::ArtistPage);
+        assert_eq!(ImageSourceKind::of_original_source("https://twitter.com/ichihaya"), ImageSourceKind::ArtistPage);
+        assert_eq!(ImageSourceKind::of_original_source("https://www.pixiv.net/artworks/12345"), ImageSourceKind::Original);
+    }
+
+    #[test]
+    fn configured_endpoints() {
+        let config = SauceConfig {
+            iqdb_address: String::from("http://localhost:5588/"),
+            gelbooru: BooruConfig::new("http://localhost:8080/", "localhost", "http"),
+            danbooru: BooruConfig::new("http://127.0.0.1:8081/", "127.0.0.1", "http"),
+            http: HttpConfig::default(),
+            cache: CacheConfig::default(),
+        };
+        let posts_json = r#"{"@attributes":{"limit":100,"offset":0,"count":1},"post":[{"id":6261499,"width":533,"height":745,"rating":"questionable","tags":"original"}]}"#;
+        let sauce = gelbooru_api::extract_md5_sauce(posts_json, &config.gelbooru).unwrap().unwrap();
+        assert_eq!(sauce.link, "http://localhost:8080/index.php?page=post&s=view&id=6261499");
+        let sauce = danbooru_api::extract_md5_sauce(r#"{"id":5012345,"image_width":1066,"image_height":1490}"#, &config.danbooru).unwrap();
+        assert_eq!(sauce.link, "http://127.0.0.1:8081/posts/5012345");
+
+        let providers = SauceProviders::from_config(&config, None).unwrap();
+        assert_eq!(providers.tag_author("http://localhost:8080/index.php?page=post&s=view&id=6261499").unwrap(), PantsuTagAuthor::Gelbooru);
+        assert_eq!(providers.tag_author("http://127.0.0.1:8081/posts/5012345").unwrap(), PantsuTagAuthor::Danbooru);
+        assert!(matches!(providers.tag_author("https://gelbooru.com/index.php?page=post&s=view&id=6261499"), Err(Error::UnsupportedSauce(_))));
+    }
+
     #[test]
     fn sort_sauce_matches() {
         let mut matches_list: Vec<SauceMatch> = vec![
@@ -181,36 +522,43 @@ mod tests {
                 link: String::from("a"),
                 similarity: 50,
                 resolution: (10,10),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("b"),
                 similarity: 60,
                 resolution: (5,5),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("c"),
                 similarity: 51,
                 resolution: (9,0),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("d"),
                 similarity: 49,
                 resolution: (20,20),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("e"),
                 similarity: 50,
                 resolution: (12,12),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("f"),
                 similarity: 50,
                 resolution: (12,0),
+                provider: String::new(),
             },
             SauceMatch {
                 link: String::from("g"),
                 similarity: 50,
                 resolution: (0,12),
+                provider: String::new(),
             },
 
         ];
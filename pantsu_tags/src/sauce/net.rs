@@ -1,24 +1,175 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use reqwest::{multipart::Part, StatusCode, Url};
+use log::warn;
+use reqwest::{multipart::Part, Client, RequestBuilder, Response, StatusCode, Url};
+use reqwest::header::RETRY_AFTER;
 use tokio::io::AsyncReadExt;
 
 use crate::{common, Error, Result};
+use super::{BooruConfig, HttpConfig};
 
-pub async fn create_image_part(image: &Path) -> Result<Part> {
-    let mut file = tokio::fs::File::open(image).await
-        .or_else(|err| Err(Error::FileNotFound(err, common::get_path(image))))?;
-    let mut bytes = Vec::new();
-    file.read_to_end(&mut bytes).await
-        .or(Err(Error::FailedRequestCreation))?;
-    let file_name = String::from(image.file_name().and_then(|name| name.to_str())
-        .ok_or(Error::FailedRequestCreation)?);
-    let mime = mime_guess::from_path(image).first_or_octet_stream();
+// upper bound for the exponential backoff and for Retry-After headers
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
 
-    Part::bytes(bytes)
-        .file_name(file_name)
-        .mime_str(mime.essence_str())
-        .or(Err(Error::FailedRequestCreation))
+/// Client shared by all sauce providers, rate limits and retries requests per host
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    rate_limiter: Arc<RateLimiter>,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(config.user_agent.as_str())
+            .timeout(Duration::from_secs(config.timeout_secs));
+        if let Some(proxy) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|_| Error::BadUrl(proxy.clone()))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(HttpClient {
+            client: builder.build()?,
+            rate_limiter: Arc::new(RateLimiter::new(config.requests_per_minute, config.burst)),
+            max_retries: config.max_retries,
+            retry_delay: Duration::from_millis(config.retry_delay_millis),
+        })
+    }
+
+    pub async fn get(&self, url: Url) -> Result<Response> {
+        self.send(url, |client, url| Ok(client.get(url))).await
+    }
+
+    // build is called again for every retry, as some request bodies (e.g. multipart forms) cannot be cloned.
+    // Throttled (429) and failed (5xx) responses are only returned after the last retry
+    pub async fn send<F>(&self, url: Url, build: F) -> Result<Response>
+        where F: Fn(&Client, Url) -> Result<RequestBuilder> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.acquire(&host).await;
+            let result = build(&self.client, url.clone())?.send().await;
+            let retry_after = match &result {
+                Ok(resp) if is_retryable_status(resp.status()) => Some(retry_after(resp)),
+                Err(e) if e.is_timeout() || e.is_connect() => Some(None),
+                _ => None,
+            };
+            match retry_after {
+                Some(retry_after) if attempt < self.max_retries => {
+                    let delay = retry_after.unwrap_or_else(|| backoff_delay(self.retry_delay, attempt))
+                        .min(MAX_RETRY_DELAY);
+                    warn!("Request to '{}' failed (attempt {}/{}), retrying in {:?}", url, attempt + 1, self.max_retries + 1, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                },
+                _ => return Ok(result?),
+            }
+        }
+    }
+}
+
+impl Default for HttpClient {
+    // like reqwest::Client::new(), this only panics if the tls backend cannot be initialized
+    fn default() -> Self {
+        HttpClient::new(&HttpConfig::default()).expect("Failed to create http client")
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn backoff_delay(retry_delay: Duration, attempt: u32) -> Duration {
+    retry_delay.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RETRY_DELAY)
+}
+
+// only the delay in seconds is supported, not the http date format
+fn retry_after(resp: &Response) -> Option<Duration> {
+    resp.headers().get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+// token bucket per host, refilled continuously with requests_per_minute tokens per minute
+struct RateLimiter {
+    requests_per_minute: u32,
+    burst: u32,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(requests_per_minute: u32, burst: u32) -> Self {
+        RateLimiter {
+            requests_per_minute,
+            burst: burst.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn acquire(&self, host: &str) {
+        while let Some(wait) = self.try_acquire(host) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // returns how long to wait until the next token is available
+    fn try_acquire(&self, host: &str) -> Option<Duration> {
+        if self.requests_per_minute == 0 {
+            return None; // rate limiting disabled
+        }
+        let tokens_per_sec = self.requests_per_minute as f64 / 60.0;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string())
+            .or_insert(TokenBucket { tokens: self.burst as f64, last_refill: now });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * tokens_per_sec).min(self.burst as f64);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64((1.0 - bucket.tokens) / tokens_per_sec))
+    }
+}
+
+// image file that is uploaded as multipart form, kept in memory so the request can be retried
+pub struct ImageUpload {
+    bytes: Vec<u8>,
+    file_name: String,
+    mime: String,
+}
+
+impl ImageUpload {
+    pub async fn read(image: &Path) -> Result<Self> {
+        let mut file = tokio::fs::File::open(image).await
+            .map_err(|err| Error::FileNotFound(err, common::get_path(image)))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).await
+            .or(Err(Error::FailedRequestCreation))?;
+        let file_name = String::from(image.file_name().and_then(|name| name.to_str())
+            .ok_or(Error::FailedRequestCreation)?);
+        let mime = mime_guess::from_path(image).first_or_octet_stream();
+        Ok(ImageUpload { bytes, file_name, mime: mime.essence_str().to_string() })
+    }
+
+    pub fn to_part(&self) -> Result<Part> {
+        Part::bytes(self.bytes.clone())
+            .file_name(self.file_name.clone())
+            .mime_str(&self.mime)
+            .or(Err(Error::FailedRequestCreation))
+    }
 }
 
 pub fn check_status(status: StatusCode) -> Result<()> {
@@ -28,21 +179,72 @@ pub fn check_status(status: StatusCode) -> Result<()> {
     Ok(())
 }
 
-pub fn https_url(url: &str) -> Result<Url> {
-    let mut https_url = Url::parse(url)
-        .or_else(|_| Err(Error::BadUrl(String::from(url))))?;
-    https_url.set_scheme("https")
-        .or_else(|_| Err(Error::BadUrl(String::from(url))))?;
-    Ok(https_url)
+pub fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url)
+        .map_err(|_| Error::BadUrl(String::from(url)))
+}
+
+// last path segment of the url, without query or fragment
+pub(crate) fn url_file_name(url: &Url) -> &str {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("file")
 }
 
-pub fn gelbooru_https_url(url: &str) -> Result<Url> {
-    let https_url = https_url(url)?;
-    let domain = https_url.domain()
+// fails if the url is not on the domain of the booru, uses the configured scheme
+pub fn booru_url(url: &str, booru: &BooruConfig) -> Result<Url> {
+    let mut booru_url = parse_url(url)?;
+    booru_url.set_scheme(&booru.scheme)
+        .map_err(|_| Error::BadUrl(String::from(url)))?;
+    let host = booru_url.host_str()
         .ok_or_else(|| Error::BadUrl(String::from(url)))?;
 
-    if !domain.ends_with("gelbooru.com") {
+    if !is_on_domain(host, &booru.domain) {
         return Err(Error::BadUrl(String::from(url)))
     }
-    Ok(https_url)
-}
\ No newline at end of file
+    Ok(booru_url)
+}
+
+// subdomains must be separated by a dot, so lookalike hosts like evilgelbooru.com don't match
+fn is_on_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::sauce::SauceConfig;
+    use super::{backoff_delay, booru_url, RateLimiter, MAX_RETRY_DELAY};
+
+    #[test]
+    fn rate_limit_per_host() {
+        let limiter = RateLimiter::new(60, 2);
+        assert!(limiter.try_acquire("gelbooru.com").is_none());
+        assert!(limiter.try_acquire("gelbooru.com").is_none());
+        let wait = limiter.try_acquire("gelbooru.com").unwrap();
+        assert!(wait.as_millis() > 900 && wait.as_millis() <= 1000);
+        assert!(limiter.try_acquire("danbooru.donmai.us").is_none());
+
+        let unlimited = RateLimiter::new(0, 1);
+        assert!((0..10).all(|_| unlimited.try_acquire("gelbooru.com").is_none()));
+    }
+
+    #[test]
+    fn backoff_delay_is_capped() {
+        let retry_delay = Duration::from_millis(500);
+        assert_eq!(backoff_delay(retry_delay, 0), retry_delay);
+        assert_eq!(backoff_delay(retry_delay, 3), Duration::from_secs(4));
+        assert_eq!(backoff_delay(retry_delay, 40), MAX_RETRY_DELAY);
+        assert_eq!(backoff_delay(Duration::MAX, u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn booru_url_domain() {
+        let gelbooru = SauceConfig::default().gelbooru;
+        assert!(booru_url("https://gelbooru.com/index.php?page=post&s=view&id=1", &gelbooru).is_ok());
+        assert!(booru_url("https://img3.gelbooru.com/images/4f/76/4f76b8d52983af1d28b1bf8d830d684e.png", &gelbooru).is_ok());
+        assert!(booru_url("https://evilgelbooru.com/index.php?page=post&s=view&id=1", &gelbooru).is_err());
+        assert!(booru_url("https://gelbooru.com.evil.org/index.php?page=post&s=view&id=1", &gelbooru).is_err());
+    }
+}
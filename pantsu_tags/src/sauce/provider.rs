@@ -0,0 +1,327 @@
+use std::path::Path;
+use futures::future::{self, BoxFuture};
+use log::warn;
+use reqwest::{StatusCode, Url};
+use crate::common::error::{Error, Result};
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagAuthor};
+use crate::{common, ImageHandle};
+use crate::file_handler::hash;
+use super::{GelbooruCredentials, SauceConfig, SauceMatch, SaucePost};
+use super::net::HttpClient;
+use super::response_cache::ResponseCache;
+use super::CacheConfig;
+use super::sauce_finder::{DanbooruMd5SauceProvider, GelbooruMd5SauceProvider, IqdbSauceProvider};
+use super::tag_finder::{DanbooruTagProvider, GelbooruTagProvider};
+
+/// Reverse image search backend, e.g. iqdb, SauceNAO or ascii2d
+pub trait SauceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn find_sauce<'a>(&'a self, image: &'a ImageHandle, lib: &'a Path) -> BoxFuture<'a, Result<Vec<SauceMatch>>>;
+}
+
+/// Exact sauce lookup by the md5 of the image file, e.g. through a booru api
+pub trait Md5SauceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Returns None if the provider does not know the md5
+    fn find_sauce_by_md5<'a>(&'a self, md5: &'a str) -> BoxFuture<'a, Result<Option<SauceMatch>>>;
+}
+
+/// Fetches the tags of a sauce link, e.g. a gelbooru or danbooru post
+pub trait TagProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Author the found tags are stored with
+    fn tag_author(&self) -> PantsuTagAuthor;
+
+    /// Returns true if this provider understands the given sauce link
+    fn supports(&self, link: &str) -> bool;
+
+    fn find_tags<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Vec<PantsuTag>>>;
+
+    /// Returns the original file of the post, providers that can't download images return UnsupportedSauce
+    fn find_post<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<SaucePost>> {
+        Box::pin(future::ready(Err(Error::UnsupportedSauce(link.to_string()))))
+    }
+
+    /// Checks that a file url of one of its posts, e.g. the original file, is hosted by the provider and
+    /// returns it. Providers that can't download images return UnsupportedSauce
+    fn file_url(&self, url: &str) -> Result<Url> {
+        Err(Error::UnsupportedSauce(url.to_string()))
+    }
+
+    /// Returns the url of a small preview of the post, providers without previews return UnsupportedSauce
+    fn find_thumbnail_url<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Url>> {
+        Box::pin(future::ready(Err(Error::UnsupportedSauce(link.to_string()))))
+    }
+
+    /// Checks if the post behind the sauce link still exists. Only a missing post or an api response
+    /// reporting it as deleted count as dead, any other error is returned, so a temporarily unreachable
+    /// booru or a changed response format doesn't flag its posts as dead
+    fn check_liveness<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<SauceLiveness>> {
+        Box::pin(async move {
+            match self.find_tags(link).await {
+                Ok(_) => Ok(SauceLiveness::Alive),
+                Err(e) if is_dead_sauce_error(&e) => Ok(SauceLiveness::Dead),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
+
+pub(crate) fn is_dead_sauce_error(error: &Error) -> bool {
+    match error {
+        Error::PostDeleted => true,
+        Error::BadResponseStatus(status) => *status == StatusCode::NOT_FOUND || *status == StatusCode::GONE,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SauceLiveness {
+    Alive,
+    // the post was deleted or moved
+    Dead,
+}
+
+pub struct SauceProviders {
+    config: SauceConfig,
+    http_client: HttpClient,
+    cache: ResponseCache,
+    md5_providers: Vec<Box<dyn Md5SauceProvider>>,
+    sauce_providers: Vec<Box<dyn SauceProvider>>,
+    tag_providers: Vec<Box<dyn TagProvider>>,
+}
+
+impl SauceProviders {
+    pub fn new() -> Self {
+        SauceProviders {
+            config: SauceConfig::default(),
+            http_client: HttpClient::default(),
+            cache: ResponseCache::disabled(),
+            md5_providers: Vec::new(),
+            sauce_providers: Vec::new(),
+            tag_providers: Vec::new(),
+        }
+    }
+
+    // the default providers with the endpoints of the given config, all of them share one http client
+    pub fn from_config(config: &SauceConfig, gelbooru_credentials: Option<GelbooruCredentials>) -> Result<Self> {
+        let client = HttpClient::new(&config.http)?;
+        let providers = SauceProviders {
+            config: config.clone(),
+            http_client: client.clone(),
+            cache: ResponseCache::new(&config.cache),
+            ..SauceProviders::new()
+        };
+        Ok(providers
+            .with_md5_provider(Box::new(GelbooruMd5SauceProvider::new(client.clone(), config.gelbooru.clone(), gelbooru_credentials.clone())))
+            .with_md5_provider(Box::new(DanbooruMd5SauceProvider::new(client.clone(), config.danbooru.clone())))
+            .with_sauce_provider(Box::new(IqdbSauceProvider::new(client.clone(), &config.iqdb_address, config.gelbooru.clone())))
+            .with_tag_provider(Box::new(GelbooruTagProvider::new(client.clone(), config.gelbooru.clone(), gelbooru_credentials)))
+            .with_tag_provider(Box::new(DanbooruTagProvider::new(client, config.danbooru.clone()))))
+    }
+
+    pub fn config(&self) -> &SauceConfig {
+        &self.config
+    }
+
+    // shared client for custom providers, it rate limits requests per host
+    pub fn http_client(&self) -> &HttpClient {
+        &self.http_client
+    }
+
+    pub fn with_cache(mut self, config: &CacheConfig) -> Self {
+        self.cache = ResponseCache::new(config);
+        self
+    }
+
+    pub fn with_md5_provider(mut self, provider: Box<dyn Md5SauceProvider>) -> Self {
+        self.md5_providers.push(provider);
+        self
+    }
+
+    pub fn with_sauce_provider(mut self, provider: Box<dyn SauceProvider>) -> Self {
+        self.sauce_providers.push(provider);
+        self
+    }
+
+    pub fn with_tag_provider(mut self, provider: Box<dyn TagProvider>) -> Self {
+        self.tag_providers.push(provider);
+        self
+    }
+
+    // Results are cached by image, as the image name contains its hash.
+    // Empty results and results of a lookup where some provider failed are not cached, so they are retried next time
+    pub async fn find_sauce(&self, image: &ImageHandle, lib: &Path) -> Result<Vec<SauceMatch>> {
+        Ok(self.find_sauce_cached(image, lib).await?.0)
+    }
+
+    // also returns whether the sauces were taken from the cache
+    pub async fn find_sauce_cached(&self, image: &ImageHandle, lib: &Path) -> Result<(Vec<SauceMatch>, bool)> {
+        if let Some(sauce_matches) = self.cache.get(&sauce_cache_key(image)).await {
+            return Ok((sauce_matches, true));
+        }
+        Ok((self.refresh_sauce(image, lib).await?, false))
+    }
+
+    // bypasses the cache and updates the cached sauces
+    pub async fn refresh_sauce(&self, image: &ImageHandle, lib: &Path) -> Result<Vec<SauceMatch>> {
+        let (sauce_matches, complete) = self.lookup_sauce(image, lib).await?;
+        if complete && !sauce_matches.is_empty() {
+            self.cache.put(&sauce_cache_key(image), &sauce_matches).await;
+        }
+        Ok(sauce_matches)
+    }
+
+    // Looks up the md5 of the image first, only images without an exact match are queried at the sauce providers.
+    // Fails only if every provider failed, otherwise also returns whether all queried providers answered
+    async fn lookup_sauce(&self, image: &ImageHandle, lib: &Path) -> Result<(Vec<SauceMatch>, bool)> {
+        let (exact_matches, md5_complete) = self.find_sauce_by_md5(image, lib).await?;
+        if !exact_matches.is_empty() {
+            return Ok((exact_matches, md5_complete));
+        }
+
+        let results = future::join_all(self.sauce_providers.iter()
+            .map(|provider| provider.find_sauce(image, lib))
+        ).await;
+
+        let mut sauce_matches: Vec<SauceMatch> = Vec::new();
+        let mut first_error: Option<Error> = None;
+        for (provider, result) in self.sauce_providers.iter().zip(results) {
+            match result {
+                Ok(matches) => merge_sauce_matches(&mut sauce_matches, with_provider(matches, provider.name())),
+                Err(e) => {
+                    warn!("Sauce provider '{}' failed for image '{}': {}", provider.name(), image.get_filename(), e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        let complete = md5_complete && first_error.is_none();
+        match first_error {
+            Some(e) if sauce_matches.is_empty() => Err(e),
+            _ => Ok((sauce_matches, complete)),
+        }
+    }
+
+    // also returns whether all md5 providers answered
+    async fn find_sauce_by_md5(&self, image: &ImageHandle, lib: &Path) -> Result<(Vec<SauceMatch>, bool)> {
+        if self.md5_providers.is_empty() {
+            return Ok((Vec::new(), true));
+        }
+        let image_path = image.get_path(lib);
+        let content = tokio::fs::read(&image_path).await
+            .map_err(|e| Error::FileNotFound(e, common::get_path(&image_path)))?;
+        let md5 = hash::get_md5_hash(&content);
+
+        let mut sauce_matches: Vec<SauceMatch> = Vec::new();
+        let mut complete = true;
+        for provider in &self.md5_providers {
+            match provider.find_sauce_by_md5(&md5).await {
+                Ok(Some(sauce_match)) => merge_sauce_matches(&mut sauce_matches, with_provider(vec![sauce_match], provider.name())),
+                Ok(None) => {},
+                Err(e) => {
+                    warn!("Md5 provider '{}' failed for image '{}': {}", provider.name(), image.get_filename(), e);
+                    complete = false;
+                },
+            }
+        }
+        Ok((sauce_matches, complete))
+    }
+
+    // tags are cached in their text format, e.g. "general:tagme"
+    pub async fn find_tags(&self, link: &str) -> Result<Vec<PantsuTag>> {
+        self.tag_provider(link)?; // unsupported links fail even if they are cached
+        let cache_key = format!("tags:{}", link);
+        if let Some(tags) = self.cache.get::<Vec<String>>(&cache_key).await {
+            match tags.iter().map(|tag| PantsuTag::deserialize(tag)).collect::<Result<Vec<_>>>() {
+                Ok(tags) => return Ok(tags),
+                Err(e) => warn!("Ignoring cached tags of '{}': {}", link, e),
+            }
+        }
+        self.refresh_tags(link).await
+    }
+
+    // bypasses the cache and updates the cached tags, empty results are not cached
+    pub async fn refresh_tags(&self, link: &str) -> Result<Vec<PantsuTag>> {
+        let provider = self.tag_provider(link)?;
+        let tags = provider.find_tags(link).await?;
+        if !tags.is_empty() {
+            self.cache.put(&format!("tags:{}", link), &tags.iter().map(|tag| tag.serialize()).collect::<Vec<_>>()).await;
+        }
+        Ok(tags)
+    }
+
+    // returns the number of removed cache entries
+    pub fn clear_cache(&self) -> Result<usize> {
+        self.cache.clear()
+    }
+
+    pub async fn find_post(&self, link: &str) -> Result<SaucePost> {
+        let provider = self.tag_provider(link)?;
+        let cache_key = format!("post:{}", link);
+        if let Some(post) = self.cache.get::<SaucePost>(&cache_key).await {
+            return Ok(post);
+        }
+        let post = provider.find_post(link).await?;
+        self.cache.put(&cache_key, &post).await;
+        Ok(post)
+    }
+
+    // the original file of the post behind the sauce link, it has to be hosted by the provider of that link
+    pub fn original_file_url(&self, link: &str, post: &SaucePost) -> Result<Url> {
+        let file_url = post.file_url.as_deref()
+            .ok_or_else(|| Error::ApiParseError(String::from("post has no file")))?;
+        self.tag_provider(link)?.file_url(file_url)
+    }
+
+    pub async fn find_thumbnail_url(&self, link: &str) -> Result<Url> {
+        self.tag_provider(link)?.find_thumbnail_url(link).await
+    }
+
+    // never cached, the cached tags of a deleted post would hide that it's gone
+    pub async fn check_liveness(&self, link: &str) -> Result<SauceLiveness> {
+        self.tag_provider(link)?.check_liveness(link).await
+    }
+
+    pub fn tag_author(&self, link: &str) -> Result<PantsuTagAuthor> {
+        Ok(self.tag_provider(link)?.tag_author())
+    }
+
+    fn tag_provider(&self, link: &str) -> Result<&dyn TagProvider> {
+        self.tag_providers.iter()
+            .find(|p| p.supports(link))
+            .map(|p| p.as_ref())
+            .ok_or_else(|| Error::UnsupportedSauce(link.to_string()))
+    }
+}
+
+impl Default for SauceProviders {
+    fn default() -> Self {
+        SauceProviders::from_config(&SauceConfig::default(), None)
+            .expect("Failed to create http client")
+    }
+}
+
+fn sauce_cache_key(image: &ImageHandle) -> String {
+    format!("sauce:{}", image.get_filename())
+}
+
+fn with_provider(sauce_matches: Vec<SauceMatch>, provider: &str) -> Vec<SauceMatch> {
+    sauce_matches.into_iter()
+        .map(|sauce_match| SauceMatch { provider: provider.to_string(), ..sauce_match })
+        .collect()
+}
+
+// if several providers found the same link, keep the match with the highest similarity
+fn merge_sauce_matches(sauce_matches: &mut Vec<SauceMatch>, new_matches: Vec<SauceMatch>) {
+    for new_match in new_matches {
+        match sauce_matches.iter_mut().find(|m| m.link == new_match.link) {
+            Some(existing) => if new_match > *existing {
+                *existing = new_match;
+            },
+            None => sauce_matches.push(new_match),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use crate::common;
+use crate::common::error::{Error, Result};
+use crate::file_handler::hash;
+use super::CacheConfig;
+
+const CACHE_FILE_EXTENSION: &str = "json";
+
+// Caches provider results as json files named after the md5 of their key.
+// Failing to read or write the cache never fails a lookup
+pub(crate) struct ResponseCache {
+    directory: Option<PathBuf>,
+    ttl: Duration,
+}
+
+// the key is stored as well, so cache files can be inspected and reused as test fixtures
+#[derive(Deserialize, Serialize)]
+struct CacheEntry<T> {
+    key: String,
+    value: T,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(config: &CacheConfig) -> Self {
+        ResponseCache {
+            directory: config.directory.clone(),
+            ttl: Duration::from_secs(config.ttl_hours * 60 * 60),
+        }
+    }
+
+    pub(crate) fn disabled() -> Self {
+        ResponseCache {
+            directory: None,
+            ttl: Duration::ZERO,
+        }
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let path = self.entry_path(key)?;
+        let modified = tokio::fs::metadata(&path).await
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        if SystemTime::now().duration_since(modified).unwrap_or_default() >= self.ttl {
+            return None;
+        }
+        let content = tokio::fs::read_to_string(&path).await.ok()?;
+        match serde_json::from_str::<CacheEntry<T>>(&content) {
+            Ok(entry) if entry.key == key => Some(entry.value),
+            _ => {
+                warn!("Ignoring invalid cache file '{}'", common::get_path(&path));
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn put<T: serde::Serialize>(&self, key: &str, value: &T) {
+        let (directory, path) = match (&self.directory, self.entry_path(key)) {
+            (Some(directory), Some(path)) => (directory, path),
+            _ => return,
+        };
+        let entry = CacheEntry { key: key.to_string(), value };
+        let content = match serde_json::to_string_pretty(&entry) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to serialize cache entry '{}': {}", key, e);
+                return;
+            }
+        };
+        if let Err(e) = tokio::fs::create_dir_all(directory).await {
+            warn!("Failed to create cache directory '{}': {}", common::get_path(directory), e);
+            return;
+        }
+        if let Err(e) = tokio::fs::write(&path, content).await {
+            warn!("Failed to write cache file '{}': {}", common::get_path(&path), e);
+        }
+    }
+
+    // returns the number of removed cache files
+    pub(crate) fn clear(&self) -> Result<usize> {
+        let directory = match &self.directory {
+            Some(directory) if directory.exists() => directory,
+            _ => return Ok(0),
+        };
+        let entries = std::fs::read_dir(directory)
+            .map_err(|e| Error::FileNotFound(e, common::get_path(directory)))?;
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry.map_err(|e| Error::FileNotFound(e, common::get_path(directory)))?.path();
+            if !is_cache_file(&path) {
+                continue;
+            }
+            std::fs::remove_file(&path)
+                .map_err(|e| Error::FileWriteError(e, common::get_path(&path)))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        self.directory.as_ref().map(|directory| {
+            directory.join(format!("{}.{}", hash::get_md5_hash(key.as_bytes()), CACHE_FILE_EXTENSION))
+        })
+    }
+}
+
+fn is_cache_file(path: &Path) -> bool {
+    path.is_file() && path.extension() == Some(OsStr::new(CACHE_FILE_EXTENSION))
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use serde_derive::{Deserialize, Serialize};
+
+const IQDB_ADDRESS: &str = "https://gelbooru.iqdb.org/";
+const GELBOORU_ADDRESS: &str = "https://gelbooru.com/";
+const GELBOORU_DOMAIN: &str = "gelbooru.com";
+const DANBOORU_ADDRESS: &str = "https://danbooru.donmai.us/";
+const DANBOORU_DOMAIN: &str = "donmai.us";
+const DEFAULT_SCHEME: &str = "https";
+const USER_AGENT: &str = concat!("pantsu_tags/", env!("CARGO_PKG_VERSION"));
+
+/// Endpoints of the sauce backends, e.g. to use a self-hosted iqdb or a local mock server
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SauceConfig {
+    pub iqdb_address: String,
+    pub gelbooru: BooruConfig,
+    pub danbooru: BooruConfig,
+    pub http: HttpConfig,
+    pub cache: CacheConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BooruConfig {
+    // md5 lookups are sent here and the found sauce links point here
+    pub address: String,
+    // sauce links are only accepted if their host is this domain or one of its subdomains
+    pub domain: String,
+    // sauce links are rewritten to this scheme before requesting them
+    pub scheme: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HttpConfig {
+    pub user_agent: String,
+    pub proxy: Option<String>,
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    // delay before the first retry, doubled for every further retry
+    pub retry_delay_millis: u64,
+    // rate limit per host, 0 disables rate limiting
+    pub requests_per_minute: u32,
+    pub burst: u32,
+    // requests sent at the same time for one lookup, e.g. thumbnail downloads
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CacheConfig {
+    // sauce and tag lookups are cached in this directory, None disables the cache
+    pub directory: Option<PathBuf>,
+    pub ttl_hours: u64,
+}
+
+impl BooruConfig {
+    pub fn new(address: &str, domain: &str, scheme: &str) -> Self {
+        BooruConfig {
+            address: address.to_string(),
+            domain: domain.to_string(),
+            scheme: scheme.to_string(),
+        }
+    }
+}
+
+impl Default for SauceConfig {
+    fn default() -> Self {
+        SauceConfig {
+            iqdb_address: String::from(IQDB_ADDRESS),
+            gelbooru: BooruConfig::new(GELBOORU_ADDRESS, GELBOORU_DOMAIN, DEFAULT_SCHEME),
+            danbooru: BooruConfig::new(DANBOORU_ADDRESS, DANBOORU_DOMAIN, DEFAULT_SCHEME),
+            http: HttpConfig::default(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            user_agent: String::from(USER_AGENT),
+            proxy: None,
+            timeout_secs: 30,
+            max_retries: 3,
+            retry_delay_millis: 1000,
+            requests_per_minute: 60,
+            burst: 4,
+            max_concurrent_requests: 16,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            directory: None,
+            ttl_hours: 7 * 24,
+        }
+    }
+}
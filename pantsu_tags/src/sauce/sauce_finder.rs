@@ -1,71 +1,163 @@
 use std::path::Path;
+use futures::future::BoxFuture;
 use futures::{stream, StreamExt};
 use log::warn;
 use reqwest::multipart::Form;
-use reqwest::Client;
 use select::document::Document;
 use select::node::Node;
 use select::predicate::{Attr, Name};
+use reqwest::Url;
 use tokio::io;
-use crate::ImageHandle;
+use crate::{common, ImageHandle};
 use crate::common::tmp_dir::TmpFile;
 use crate::common::tmp_dir_async;
 use crate::common::error::Error;
 use crate::common::error::Result;
-use super::{SauceMatch, net};
-use super::image_preparer;
+use super::{BooruConfig, Md5SauceProvider, SauceConfig, SauceMatch, SaucePost, SauceProvider, SauceProviders, net};
+use super::net::{HttpClient, ImageUpload};
+use super::{danbooru_api, image_preparer};
+use super::gelbooru_api::{self, GelbooruCredentials};
 
-const IQDB_ADDRESS: &str = "https://gelbooru.iqdb.org/";
-const MAX_CONCURRENT_REQUESTS: usize = 16;
 const THUMBNAIL_TMP_SUBDIR: &str = "thumbnails";
+const ORIGINAL_TMP_SUBDIR: &str = "originals";
+
+pub struct IqdbSauceProvider {
+    client: HttpClient,
+    address: String,
+    booru: BooruConfig, // booru the iqdb instance links to
+}
+
+impl IqdbSauceProvider {
+    pub fn new(client: HttpClient, address: &str, booru: BooruConfig) -> Self {
+        IqdbSauceProvider { client, address: address.to_string(), booru }
+    }
+}
+
+impl Default for IqdbSauceProvider {
+    fn default() -> Self {
+        let config = SauceConfig::default();
+        IqdbSauceProvider::new(HttpClient::default(), &config.iqdb_address, config.gelbooru)
+    }
+}
+
+impl SauceProvider for IqdbSauceProvider {
+    fn name(&self) -> &str {
+        "iqdb"
+    }
+
+    fn find_sauce<'a>(&'a self, image: &'a ImageHandle, lib: &'a Path) -> BoxFuture<'a, Result<Vec<SauceMatch>>> {
+        Box::pin(find_sauce_iqdb(&self.client, &self.address, &self.booru, image, lib))
+    }
+}
+
+pub struct GelbooruMd5SauceProvider {
+    client: HttpClient,
+    booru: BooruConfig,
+    credentials: Option<GelbooruCredentials>,
+}
+
+impl GelbooruMd5SauceProvider {
+    pub fn new(client: HttpClient, booru: BooruConfig, credentials: Option<GelbooruCredentials>) -> Self {
+        GelbooruMd5SauceProvider { client, booru, credentials }
+    }
+}
+
+impl Default for GelbooruMd5SauceProvider {
+    fn default() -> Self {
+        GelbooruMd5SauceProvider::new(HttpClient::default(), SauceConfig::default().gelbooru, None)
+    }
+}
+
+impl Md5SauceProvider for GelbooruMd5SauceProvider {
+    fn name(&self) -> &str {
+        "gelbooru-md5"
+    }
+
+    fn find_sauce_by_md5<'a>(&'a self, md5: &'a str) -> BoxFuture<'a, Result<Option<SauceMatch>>> {
+        Box::pin(gelbooru_api::find_sauce_by_md5(&self.client, md5, &self.booru, self.credentials.as_ref()))
+    }
+}
+
+pub struct DanbooruMd5SauceProvider {
+    client: HttpClient,
+    booru: BooruConfig,
+}
+
+impl DanbooruMd5SauceProvider {
+    pub fn new(client: HttpClient, booru: BooruConfig) -> Self {
+        DanbooruMd5SauceProvider { client, booru }
+    }
+}
+
+impl Default for DanbooruMd5SauceProvider {
+    fn default() -> Self {
+        DanbooruMd5SauceProvider::new(HttpClient::default(), SauceConfig::default().danbooru)
+    }
+}
+
+impl Md5SauceProvider for DanbooruMd5SauceProvider {
+    fn name(&self) -> &str {
+        "danbooru-md5"
+    }
+
+    fn find_sauce_by_md5<'a>(&'a self, md5: &'a str) -> BoxFuture<'a, Result<Option<SauceMatch>>> {
+        Box::pin(danbooru_api::find_sauce_by_md5(&self.client, md5, &self.booru))
+    }
+}
 
 // image path has to point to an image, otherwise returns an Error::HtmlParseError
-pub async fn find_sauce(image_handle: &ImageHandle, lib_path: &Path) -> Result<Vec<SauceMatch>> {
+async fn find_sauce_iqdb(client: &HttpClient, iqdb_address: &str, booru: &BooruConfig, image_handle: &ImageHandle, lib_path: &Path) -> Result<Vec<SauceMatch>> {
     let image = image_preparer::prepare_image(image_handle, lib_path).await?;
-    let client = Client::new();
-    let image_part = net::create_image_part(image.get_path()).await?;
-    let form = Form::new()
-        .part("file", image_part);
-    let response = client.post(net::https_url(IQDB_ADDRESS)?)
-        .multipart(form)
-        .send().await?;
+    let upload = ImageUpload::read(image.get_path()).await?;
+    let response = client.send(net::parse_url(iqdb_address)?, |client, url| {
+        let form = Form::new()
+            .part("file", upload.to_part()?);
+        Ok(client.post(url).multipart(form))
+    }).await?;
     net::check_status(response.status())?;
 
     let response = response.text().await?;
     let html = Document::from(response.as_str());
-    extract_sauce(&html)
-}
-
-// explanation: https://stackoverflow.com/a/51047786
-pub async fn get_thumbnails(sauces: &Vec<SauceMatch>) -> Result<Vec<TmpFile>> {
-    let client = reqwest::Client::new();
-    let thumbnails = stream::iter(sauces)
-        .map(|sauce| {
-            let client = &client;
-            async move {
-                let resp = client.get(net::gelbooru_https_url(&sauce.link)?).send().await?;
-                net::check_status(resp.status())?;
-                let text = resp.text().await
-                    .map_err(|_| Error::FailedThumbnail)?;
-                let link = extract_thumbnail_link(&text)?;
-
-                let resp = client.get(net::gelbooru_https_url(&link)?).send().await?;
-                net::check_status(resp.status())?;
-                let data = resp.bytes().await?;
-                let path = store_thumbnail(&link, data.as_ref()).await?;
-                Ok((path,sauce))
-            }
-        })
-        .buffered(MAX_CONCURRENT_REQUESTS)
-        .collect::<Vec<Result<(TmpFile,&SauceMatch)>>>().await;
+    extract_sauce(&html, booru)
+}
+
+// Returns one result per sauce in the order of the sauces, so a single missing thumbnail doesn't lose the others.
+// The thumbnail is looked up at the tag provider of each sauce
+pub async fn get_thumbnails(providers: &SauceProviders, sauces: &[SauceMatch]) -> Vec<Result<TmpFile>> {
+    stream::iter(sauces)
+        .map(|sauce| get_thumbnail(providers, sauce))
+        .buffered(providers.config().http.max_concurrent_requests)
+        .collect().await
+}
 
-    let thumbnails = thumbnails.into_iter().collect::<Result<Vec<(TmpFile,&SauceMatch)>>>()?;
-    // make sure that the vec of links has the same order as the vec of Sauces
-    Ok(thumbnails.into_iter().enumerate()
-        .map(|(idx,(path,sauce))| {
-            assert!(*sauce == sauces[idx]);
-            path
-        }).collect())
+async fn get_thumbnail(providers: &SauceProviders, sauce: &SauceMatch) -> Result<TmpFile> {
+    let link = providers.find_thumbnail_url(&sauce.link).await?;
+    let resp = providers.http_client().get(link.clone()).await?;
+    net::check_status(resp.status())?;
+    let data = resp.bytes().await?;
+    store_thumbnail(&link, data.as_ref()).await
+}
+
+// the thumbnail has to be on the booru or one of its cdn subdomains
+pub(crate) async fn find_thumbnail_url_gelbooru(client: &HttpClient, link: &str, booru: &BooruConfig) -> Result<Url> {
+    let resp = client.get(net::booru_url(link, booru)?).await?;
+    net::check_status(resp.status())?;
+    let text = resp.text().await
+        .map_err(|_| Error::FailedThumbnail)?;
+    net::booru_url(&extract_thumbnail_link(&text)?, booru)
+}
+
+// the file keeps the name of the original, so its extension can be used to import it
+pub(crate) async fn download_original(providers: &SauceProviders, sauce_link: &str, post: &SaucePost) -> Result<TmpFile> {
+    let file_url = providers.original_file_url(sauce_link, post)?;
+    let resp = providers.http_client().get(file_url.clone()).await?;
+    net::check_status(resp.status())?;
+    let data = resp.bytes().await?;
+    let file_name = net::url_file_name(&file_url);
+    let (path,mut file) = tmp_dir_async::create_tmp_file(ORIGINAL_TMP_SUBDIR, file_name).await?;
+    io::copy(&mut data.as_ref(), &mut file).await
+        .map_err(|e| Error::FileWriteError(e, common::get_path(path.get_path())))?;
+    Ok(path)
 }
 
 // Extract thumbnail from Gelbooru page
@@ -75,15 +167,15 @@ fn extract_thumbnail_link(html_text: &str) -> Result<String> {
     image.attr("src").ok_or(Error::HtmlParseError).map(|link| link.to_owned())
 }
 
-async fn store_thumbnail(link: &str, data: &[u8]) -> Result<TmpFile> {
-    let file_name = link.rsplit_once('/').map(|(_,name)| name ).unwrap_or(link);
+async fn store_thumbnail(link: &Url, data: &[u8]) -> Result<TmpFile> {
+    let file_name = net::url_file_name(link);
     let (path,mut file) = tmp_dir_async::create_tmp_file(THUMBNAIL_TMP_SUBDIR, file_name).await?;
     io::copy(&mut data.as_ref(), &mut file).await
         .or(Err(Error::FailedThumbnail))?;
     Ok(path)
 }
 
-fn extract_sauce(html: &Document) -> Result<Vec<SauceMatch>> {
+fn extract_sauce(html: &Document, booru: &BooruConfig) -> Result<Vec<SauceMatch>> {
     let mut pages = html.find(Attr("id", "pages"));
     let pages = pages.next().ok_or(Error::HtmlParseError)?; // html element "pages" should always exist, even if there are no relevant matches. Maybe file wasn't an image?
     let mut res: Vec<SauceMatch> = Vec::new();
@@ -100,7 +192,7 @@ fn extract_sauce(html: &Document) -> Result<Vec<SauceMatch>> {
             match idx {
                 0 => continue,
                 1 => {
-                    sauce_link = extract_sauce_link(node);
+                    sauce_link = extract_sauce_link(node, booru);
                     if let None = sauce_link {
                         continue;
                     }
@@ -119,6 +211,7 @@ fn extract_sauce(html: &Document) -> Result<Vec<SauceMatch>> {
                 link,
                 similarity,
                 resolution,
+                provider: String::new(),
             };
             res.push(sauce_match);
         }
@@ -131,7 +224,7 @@ fn extract_sauce(html: &Document) -> Result<Vec<SauceMatch>> {
     Ok(res)
 }
 
-fn extract_sauce_link(sauce_match_tr_element: Node) -> Option<String> {
+fn extract_sauce_link(sauce_match_tr_element: Node, booru: &BooruConfig) -> Option<String> {
     let td_or_th = sauce_match_tr_element.first_child();
     if td_or_th.is_none() {
         return None;
@@ -151,7 +244,7 @@ fn extract_sauce_link(sauce_match_tr_element: Node) -> Option<String> {
         return None
     }
     let href = href.unwrap();
-    match net::gelbooru_https_url(href) {
+    match net::booru_url(href, booru) {
         Ok(url) => Some(url.to_string()),
         Err(_) => {
             warn!("Found bad url when extracting sauce link: {}", href);
@@ -0,0 +1,132 @@
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use crate::common::error::{Error, Result};
+use crate::common::pantsu_tag::PantsuTag;
+use crate::image_similarity;
+use crate::ImageInfo;
+use super::{SauceMatch, SauceProviders, provider, sauce_finder};
+
+/// Decides which sauce matches of a lookup are accepted, reviewed by a human or discarded
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SauceJudgePolicy {
+    // sauce matches with a higher similarity are accepted
+    pub found_similarity: i32,
+    // sauce matches with a higher similarity are relevant, others are discarded
+    pub relevant_similarity: i32,
+    // number of images looked up at the same time
+    pub max_concurrent_lookups: usize,
+    // number of unsure images whose thumbnails are downloaded ahead of the review
+    pub max_prefetched_reviews: usize,
+    // compare the thumbnails of unsure sauces with the local image
+    pub auto_judge: bool,
+    // an unsure sauce with at least this confidence is accepted
+    pub accept_confidence: f32,
+    // unsure sauces below this confidence are discarded
+    pub reject_confidence: f32,
+}
+
+pub enum SauceJudgement {
+    // auto_judged: an unsure sauce was judged by comparing it with the local image
+    Matching { sauce: SauceMatch, tags: Vec<PantsuTag>, auto_judged: bool },
+    Unsure(Vec<SauceMatch>),
+    NotExisting { auto_judged: bool },
+}
+
+impl SauceJudgePolicy {
+    pub fn relevant_sauces(&self, sauces: Vec<SauceMatch>) -> Vec<SauceMatch> {
+        sauces.into_iter()
+            .filter(|s| s.similarity > self.relevant_similarity)
+            .collect()
+    }
+}
+
+impl Default for SauceJudgePolicy {
+    fn default() -> Self {
+        SauceJudgePolicy {
+            found_similarity: 90,
+            relevant_similarity: 45,
+            max_concurrent_lookups: 16,
+            max_prefetched_reviews: 4,
+            auto_judge: true,
+            accept_confidence: 0.85,
+            reject_confidence: 0.3,
+        }
+    }
+}
+
+// the tags of the accepted sauce bypass the cache if refresh is set
+pub(crate) async fn judge_sauce(providers: &SauceProviders, policy: &SauceJudgePolicy, image: &ImageInfo, sauces: Vec<SauceMatch>, refresh: bool) -> Result<SauceJudgement> {
+    let (good_sauces, unsure_sauces): (Vec<SauceMatch>, Vec<SauceMatch>) = policy.relevant_sauces(sauces).into_iter()
+        .partition(|s| s.similarity > policy.found_similarity);
+
+    if let Some((sauce, tags)) = get_first_sauce_tags(providers, good_sauces, refresh).await? {
+        return Ok(SauceJudgement::Matching { sauce, tags, auto_judged: false });
+    }
+
+    if unsure_sauces.is_empty() {
+        return Ok(SauceJudgement::NotExisting { auto_judged: false });
+    }
+    if !policy.auto_judge {
+        return Ok(SauceJudgement::Unsure(unsure_sauces));
+    }
+    judge_unsure_sauces(providers, policy, image, unsure_sauces, refresh).await
+}
+
+// compares the thumbnails of the unsure sauces with the local image to spare the user from reviewing clear cases
+async fn judge_unsure_sauces(providers: &SauceProviders, policy: &SauceJudgePolicy, image: &ImageInfo, sauces: Vec<SauceMatch>, refresh: bool) -> Result<SauceJudgement> {
+    let confidences = get_sauce_confidences(providers, image, &sauces).await;
+    if confidences.iter().all(Option::is_none) {
+        warn!("Failed to judge sauces of image '{}' automatically", image.get_image().get_filename());
+        return Ok(SauceJudgement::Unsure(sauces));
+    }
+    // sauces that couldn't be compared are neither rejected nor accepted, they are left for the review
+    let mut candidates: Vec<(SauceMatch, Option<f32>)> = sauces.into_iter().zip(confidences)
+        .filter(|(_, confidence)| !matches!(confidence, Some(confidence) if *confidence < policy.reject_confidence))
+        .collect();
+    if candidates.is_empty() {
+        info!("Rejected all sauces of image '{}' automatically", image.get_image().get_filename());
+        return Ok(SauceJudgement::NotExisting { auto_judged: true });
+    }
+
+    candidates.sort_by(|(_, a), (_, b)| b.unwrap_or(f32::MIN).total_cmp(&a.unwrap_or(f32::MIN)));
+    let accepted = candidates.iter()
+        .take_while(|(_, confidence)| matches!(confidence, Some(confidence) if *confidence >= policy.accept_confidence))
+        .map(|(sauce, _)| sauce.clone())
+        .collect();
+    if let Some((sauce, tags)) = get_first_sauce_tags(providers, accepted, refresh).await? {
+        info!("Accepted sauce '{}' of image '{}' automatically", sauce.link, image.get_image().get_filename());
+        return Ok(SauceJudgement::Matching { sauce, tags, auto_judged: true });
+    }
+    Ok(SauceJudgement::Unsure(candidates.into_iter().map(|(sauce, _)| sauce).collect()))
+}
+
+// None for sauces whose thumbnail couldn't be downloaded or compared
+async fn get_sauce_confidences(providers: &SauceProviders, image: &ImageInfo, sauces: &[SauceMatch]) -> Vec<Option<f32>> {
+    let thumbnails = sauce_finder::get_thumbnails(providers, sauces).await;
+    sauces.iter().zip(thumbnails)
+        .map(|(sauce, thumbnail)| {
+            let confidence = thumbnail.and_then(|thumbnail| image_similarity::get_sauce_confidence(image, sauce, thumbnail.get_path()));
+            if let Err(e) = &confidence {
+                warn!("Failed to compare sauce '{}' with image '{}': {}", sauce.link, image.get_image().get_filename(), e);
+            }
+            confidence.ok()
+        })
+        .collect()
+}
+
+// returns the first sauce whose tags can be retrieved
+async fn get_first_sauce_tags(providers: &SauceProviders, sauces: Vec<SauceMatch>, refresh: bool) -> Result<Option<(SauceMatch, Vec<PantsuTag>)>> {
+    for sauce in sauces {
+        let tags = match refresh {
+            true => providers.refresh_tags(&sauce.link).await,
+            false => providers.find_tags(&sauce.link).await,
+        };
+        match tags {
+            Ok(tags) => return Ok(Some((sauce, tags))),
+            Err(Error::HtmlParseError) => continue,    // Html error can happen if image was deleted on gelbooru, try next sauceMatch
+            Err(e) if provider::is_dead_sauce_error(&e) => continue,
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(None)
+}
@@ -1,17 +1,148 @@
-use reqwest::Client;
+use futures::future::BoxFuture;
+use log::warn;
+use reqwest::{StatusCode, Url};
 use select::document::Document;
 use select::predicate::Attr;
 use enum_iterator::IntoEnumIterator;
 use select::node::Node;
 use crate::common::error::Error;
-use crate::common::pantsu_tag::{PantsuTag, PantsuTagType};
+use crate::common::pantsu_tag::{PantsuTag, PantsuTagAuthor, PantsuTagType};
+use crate::common::rating::Rating;
 
-use super::net;
+use super::{BooruConfig, SauceConfig, SauceLiveness, SaucePost, TagProvider, net, provider};
+use super::net::HttpClient;
+use super::{danbooru_api, sauce_finder};
+use super::gelbooru_api::{self, GelbooruCredentials};
+
+pub struct GelbooruTagProvider {
+    client: HttpClient,
+    booru: BooruConfig,
+    credentials: Option<GelbooruCredentials>,
+}
+
+impl GelbooruTagProvider {
+    pub fn new(client: HttpClient, booru: BooruConfig, credentials: Option<GelbooruCredentials>) -> Self {
+        GelbooruTagProvider { client, booru, credentials }
+    }
+}
+
+impl Default for GelbooruTagProvider {
+    fn default() -> Self {
+        GelbooruTagProvider::new(HttpClient::default(), SauceConfig::default().gelbooru, None)
+    }
+}
+
+impl TagProvider for GelbooruTagProvider {
+    fn name(&self) -> &str {
+        "gelbooru"
+    }
+
+    fn tag_author(&self) -> PantsuTagAuthor {
+        PantsuTagAuthor::Gelbooru
+    }
+
+    fn supports(&self, link: &str) -> bool {
+        net::booru_url(link, &self.booru).is_ok()
+    }
+
+    // uses the gelbooru api, scraping the html page is only a fallback
+    fn find_tags<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Vec<PantsuTag>, Error>> {
+        Box::pin(async move {
+            match gelbooru_api::find_tags_gelbooru_api(&self.client, link, &self.booru, self.credentials.as_ref()).await {
+                Ok(tags) => Ok(tags),
+                Err(e) if can_fall_back_to_html(&e) => {
+                    warn!("Gelbooru api lookup failed for '{}', falling back to html scraping: {}", link, e);
+                    find_tags_gelbooru(&self.client, link, &self.booru).await
+                },
+                Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn find_post<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<SaucePost, Error>> {
+        Box::pin(gelbooru_api::find_post_gelbooru_api(&self.client, link, &self.booru, self.credentials.as_ref()))
+    }
+
+    // the files are served by cdn subdomains of the booru
+    fn file_url(&self, url: &str) -> Result<Url, Error> {
+        net::booru_url(url, &self.booru)
+    }
+
+    fn find_thumbnail_url<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Url, Error>> {
+        Box::pin(sauce_finder::find_thumbnail_url_gelbooru(&self.client, link, &self.booru))
+    }
+}
+
+pub struct DanbooruTagProvider {
+    client: HttpClient,
+    booru: BooruConfig,
+}
+
+impl DanbooruTagProvider {
+    pub fn new(client: HttpClient, booru: BooruConfig) -> Self {
+        DanbooruTagProvider { client, booru }
+    }
+}
+
+impl Default for DanbooruTagProvider {
+    fn default() -> Self {
+        DanbooruTagProvider::new(HttpClient::default(), SauceConfig::default().danbooru)
+    }
+}
+
+impl TagProvider for DanbooruTagProvider {
+    fn name(&self) -> &str {
+        "danbooru"
+    }
+
+    fn tag_author(&self) -> PantsuTagAuthor {
+        PantsuTagAuthor::Danbooru
+    }
+
+    fn supports(&self, link: &str) -> bool {
+        net::booru_url(link, &self.booru).is_ok()
+    }
+
+    fn find_tags<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Vec<PantsuTag>, Error>> {
+        Box::pin(danbooru_api::find_tags_danbooru(&self.client, link, &self.booru))
+    }
+
+    fn find_post<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<SaucePost, Error>> {
+        Box::pin(danbooru_api::find_post_danbooru(&self.client, link, &self.booru))
+    }
+
+    // the files are served by cdn subdomains of the booru
+    fn file_url(&self, url: &str) -> Result<Url, Error> {
+        net::booru_url(url, &self.booru)
+    }
+
+    fn find_thumbnail_url<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<Url, Error>> {
+        Box::pin(danbooru_api::find_thumbnail_url_danbooru(&self.client, link, &self.booru))
+    }
+
+    fn check_liveness<'a>(&'a self, link: &'a str) -> BoxFuture<'a, Result<SauceLiveness, Error>> {
+        Box::pin(async move {
+            match danbooru_api::check_liveness_danbooru(&self.client, link, &self.booru).await {
+                Err(e) if provider::is_dead_sauce_error(&e) => Ok(SauceLiveness::Dead),
+                result => result,
+            }
+        })
+    }
+}
+
+// The html page only helps if the api answered with something unusable or refused the credentials.
+// Rate limits and server errors would only send more requests to a struggling host
+pub(crate) fn can_fall_back_to_html(api_error: &Error) -> bool {
+    match api_error {
+        Error::ApiParseError(_) => true,
+        Error::BadResponseStatus(status) => *status == StatusCode::UNAUTHORIZED || *status == StatusCode::FORBIDDEN,
+        _ => false,
+    }
+}
 
 // If image was deleted on gelbooru, throws an HtmlParseError
-pub async fn find_tags_gelbooru(url: &str) -> Result<Vec<PantsuTag>, Error> {
-    let client = Client::new();
-    let resp = client.get(net::gelbooru_https_url(url)?).send().await?;
+pub async fn find_tags_gelbooru(client: &HttpClient, url: &str, booru: &BooruConfig) -> Result<Vec<PantsuTag>, Error> {
+    let resp = client.get(net::booru_url(url, booru)?).await?;
     net::check_status(resp.status())?;
     let text = resp.text().await?;
     let html = Document::from(text.as_str());
@@ -38,10 +169,7 @@ fn extract_tags_of_type(tag_list: &Node, tag_type: PantsuTagType, result: &mut V
         for tag in tags {
             for node in tag.children() {
                 if node.is(Attr("href", ())) {
-                    result.push(PantsuTag {
-                        tag_name: node.text(),
-                        tag_type,
-                    });
+                    result.push(PantsuTag::new(node.text(), tag_type));
                 }
             }
         }
@@ -52,10 +180,10 @@ fn extract_rating(tag_list: &Node, result: &mut Vec<PantsuTag>) -> Result<(), Er
     for tag in tag_list.children() {
         match tag.text().strip_prefix("Rating: ") {
             Some(rating) => {
-                result.push(PantsuTag {
-                    tag_name: String::from(rating.trim()),
-                    tag_type: PantsuTagType::Rating,
-                });
+                match Rating::deserialize(rating) {
+                    Ok(rating) => result.push(rating.to_tag()),
+                    Err(e) => warn!("{}", e),
+                }
                 return Ok(());
             }
             None => {}
@@ -1,6 +1,6 @@
-use clap::{Parser, ArgGroup, AppSettings};
+use clap::{Parser, Subcommand, ArgGroup, AppSettings};
 use std::path::PathBuf;
-use pantsu_tags::{PantsuTagType};
+use pantsu_tags::{PantsuTagType, Rating};
 
 #[derive(Debug, Parser)]
 #[clap(name = "PantsuTags", about = "PantsuTags CLI", setting = AppSettings::SubcommandPrecedenceOverArg)]
@@ -10,11 +10,26 @@ pub enum Args {
     AddTags(AddTagsArgs),
     RemoveTags(RemoveTagsArgs),
     ListTags(ListTagsArgs),
+    RelatedTags(RelatedTagsArgs),
+    SearchTags(SearchTagsArgs),
+    // renames existing tags with the configured tag normalizer
+    NormalizeTags,
     ImageInfos(ImageInfosArgs),
     ListImages(ListImagesArgs),
     AutoLookupTags(AutoLookupTagsArgs),
     ImportTags(ImportTagsArgs),
-    ExportTags(ExportTagsArgs)
+    ExportTags(ExportTagsArgs),
+    MergeImages(MergeImagesArgs),
+    ReviewSauces(ReviewSaucesArgs),
+    RefreshTags(RefreshTagsArgs),
+    CheckSauces(CheckSaucesArgs),
+    UpgradeImages(UpgradeImagesArgs),
+    SetSauce(SetSauceArgs),
+    ClearSauce(ClearSauceArgs),
+    SuggestTags(SuggestTagsArgs),
+    #[cfg(feature = "ai-tagger")]
+    AiTag(AiTagArgs),
+    Cache(CacheArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -61,6 +76,31 @@ pub struct ListTagsArgs {
 
     #[clap(short='p', long)]
     pub print_tagnames: bool,
+
+    // number of images per tag, most used first. Only for the list of all tags
+    #[clap(short='c', long, conflicts_with="images")]
+    pub counts: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct RelatedTagsArgs {
+    // the related tags are found on the images with all of these tags
+    #[clap(required=true, min_values=1)]
+    pub tags: Vec<String>,
+    #[clap(short, long="types", min_values(1), parse(try_from_str))]
+    pub tag_types: Vec<PantsuTagType>,
+    #[clap(short, long, default_value="20")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct SearchTagsArgs {
+    // a glob pattern like '*hair' or 'character:*', other text is searched as prefix
+    pub pattern: String,
+    #[clap(short, long="types", min_values(1), parse(try_from_str))]
+    pub tag_types: Vec<PantsuTagType>,
+    #[clap(short='p', long)]
+    pub print_tagnames: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -70,10 +110,17 @@ pub struct ImageInfosArgs {
     
     #[clap(long="sort-by")]
     pub sort_order: Vec<String>,
+
+    // overrides max_rating of the config, images without a rating are left out while one applies
+    #[clap(short='r', long, parse(try_from_str), conflicts_with="all-ratings")]
+    pub max_rating: Option<Rating>,
+    // ignores max_rating of the config, e.g. to include images without a rating
+    #[clap(short='a', long)]
+    pub all_ratings: bool,
 }
 
 #[derive(Debug, Parser)]
-#[clap(group(ArgGroup::new("sauce").args(&["sauce-existing", "sauce-not-existing", "sauce-not-checked"])))]
+#[clap(group(ArgGroup::new("sauce").args(&["sauce-existing", "sauce-not-existing", "sauce-not-checked", "sauce-unsure"])))]
 pub struct ListImagesArgs {
     #[clap(short, long, min_values(1))]
     pub include_tags: Vec<String>,
@@ -94,13 +141,22 @@ pub struct ListImagesArgs {
     pub sauce_not_existing: bool,
     #[clap(short='c', long)]
     pub sauce_not_checked: bool,
+    #[clap(long)]
+    pub sauce_unsure: bool,
 
     #[clap(long="sort-by")]
     pub sort_order: Vec<String>,
+
+    // overrides max_rating of the config, images without a rating are left out while one applies
+    #[clap(short='r', long, parse(try_from_str), conflicts_with="all-ratings")]
+    pub max_rating: Option<Rating>,
+    // ignores max_rating of the config, e.g. to include images without a rating
+    #[clap(short='a', long)]
+    pub all_ratings: bool,
 }
 
 #[derive(Debug, Parser)]
-#[clap(group(ArgGroup::new("sauce").args(&["sauce-existing", "sauce-not-existing", "sauce-not-checked"])), arg_required_else_help = true)]
+#[clap(group(ArgGroup::new("sauce").args(&["sauce-existing", "sauce-not-existing", "sauce-not-checked", "recheck-older-than"])), arg_required_else_help = true)]
 pub struct AutoLookupTagsArgs {
     #[clap(short, long, min_values(1), parse(from_os_str))]
     pub images: Vec<PathBuf>,
@@ -113,6 +169,23 @@ pub struct AutoLookupTagsArgs {
     pub sauce_not_existing: bool,
     #[clap(short='c', long)]
     pub sauce_not_checked: bool,
+    // not existing sauces whose last lookup is older than this, e.g. '30d'. The age doubles with every failed lookup
+    #[clap(short='r', long)]
+    pub recheck_older_than: Option<String>,
+
+    // overrides of the sauce judge policy from the configuration
+    #[clap(long)]
+    pub found_similarity: Option<i32>,
+    #[clap(long)]
+    pub relevant_similarity: Option<i32>,
+    #[clap(long)]
+    pub max_concurrent_lookups: Option<usize>,
+    #[clap(long)]
+    pub no_auto_judge: bool,
+    #[clap(long)]
+    pub accept_confidence: Option<f32>,
+    #[clap(long)]
+    pub reject_confidence: Option<f32>,
 }
 
 #[derive(Debug, Parser)]
@@ -125,4 +198,112 @@ pub struct ImportTagsArgs {
 pub struct ExportTagsArgs {
     #[clap(short, long, parse(from_os_str))]
     pub file: PathBuf,
-}
\ No newline at end of file
+}
+#[derive(Debug, Parser)]
+pub struct MergeImagesArgs {
+    #[clap(short, long, parse(from_os_str))]
+    pub keep: PathBuf,
+    #[clap(short, long, parse(from_os_str))]
+    pub discard: PathBuf,
+    #[clap(short='a', long)]
+    pub keep_alias: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct ReviewSaucesArgs {
+    #[clap(long)]
+    pub no_feh: bool,
+}
+
+#[derive(Debug, Parser)]
+#[clap(group(ArgGroup::new("query").args(&["include-tags", "exclude-tags", "older-than-days"]).multiple(true).conflicts_with("images")))]
+pub struct RefreshTagsArgs {
+    #[clap(short, long, min_values(1), parse(from_os_str))]
+    pub images: Vec<PathBuf>,
+    #[clap(short='t', long, min_values(1))]
+    pub include_tags: Vec<String>,
+    #[clap(short, long, min_values(1))]
+    pub exclude_tags: Vec<String>,
+    #[clap(short='o', long)]
+    pub older_than_days: Option<u64>,
+    #[clap(short='d', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CheckSaucesArgs {
+    #[clap(short, long, min_values(1), parse(from_os_str))]
+    pub images: Vec<PathBuf>,
+    // try the other stored candidates, or look the image up again, if a sauce is dead
+    #[clap(short, long)]
+    pub fallback: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct UpgradeImagesArgs {
+    #[clap(short, long, min_values(1), parse(from_os_str))]
+    pub images: Vec<PathBuf>,
+    // only list the images with a larger original
+    #[clap(short='d', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct SetSauceArgs {
+    #[clap(short, long, parse(from_os_str))]
+    pub image: PathBuf,
+    pub url: String,
+    // remove the tags of the replaced sauce without asking
+    #[clap(short='t', long)]
+    pub remove_tags: bool,
+    // keep the tags of the replaced sauce without asking
+    #[clap(short='k', long, conflicts_with="remove-tags")]
+    pub keep_tags: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct ClearSauceArgs {
+    #[clap(short, long, parse(from_os_str), required=true, min_values=1)]
+    pub images: Vec<PathBuf>,
+    // remove the tags that were found on the cleared sauce without asking
+    #[clap(short='t', long)]
+    pub remove_tags: bool,
+    // keep the tags that were found on the cleared sauce without asking
+    #[clap(short='k', long, conflicts_with="remove-tags")]
+    pub keep_tags: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct SuggestTagsArgs {
+    // defaults to all images without tags
+    #[clap(short, long, min_values(1), parse(from_os_str))]
+    pub images: Vec<PathBuf>,
+    // number of similar tagged images the suggestions are taken from
+    #[clap(short='k', long, default_value="5")]
+    pub neighbours: usize,
+    // add all suggestions with at least this confidence without asking
+    #[clap(short='t', long)]
+    pub threshold: Option<f32>,
+}
+
+#[cfg(feature = "ai-tagger")]
+#[derive(Debug, Parser)]
+pub struct AiTagArgs {
+    // defaults to all images without an existing sauce
+    #[clap(short, long, min_values(1), parse(from_os_str))]
+    pub images: Vec<PathBuf>,
+    // only print the predicted tags
+    #[clap(short='d', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CacheArgs {
+    #[clap(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    Clear,
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+use colored::Colorize;
+use log::{info, warn};
+use pantsu_tags::{ImageInfo, ModelTagger, ModelTaggerConfig, PantsuTag, PantsuTagAuthor};
+use pantsu_tags::db::PantsuDB;
+use crate::common::{AppResult, AppError};
+use crate::{common, CONFIGURATION};
+
+pub fn ai_tag(image_paths: Vec<PathBuf>, dry_run: bool, config: &ModelTaggerConfig) -> AppResult<()> {
+    let mut pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths)?;
+    if images.is_empty() {
+        println!("No images without a sauce to tag");
+        return Ok(());
+    }
+    let tagger = ModelTagger::new(config)?;
+
+    let mut tagged = 0;
+    let mut failed = 0;
+    for image in images {
+        let image_name = image.get_image().get_filename();
+        let predictions = match tagger.tag_image(&image.get_image().get_path(&CONFIGURATION.library_path)) {
+            Ok(predictions) => predictions,
+            Err(e) => {
+                failed += 1;
+                println!("{} - {}: {}", "Failed to tag image".red(), image_name, e);
+                warn!("Failed to tag image '{}' with the model: {}", image_name, e);
+                continue;
+            }
+        };
+        println!("{}", image_name);
+        for (tag, confidence) in &predictions {
+            println!("  {} ({:.0}%)", tag, confidence * 100.0);
+        }
+        if dry_run || predictions.is_empty() {
+            continue;
+        }
+        let tags = predictions.into_iter().map(|(tag, _)| tag).collect::<Vec<PantsuTag>>();
+        // tags the image already has keep their author
+        pdb.update_images_transaction()
+            .for_image(image.get_image())
+            .add_tags(&tags)
+            .tag_author(&PantsuTagAuthor::Model)
+            .execute()?;
+        tagged += 1;
+        info!("Added model tags {} to image: '{}'", PantsuTag::display_vec(&tags), image_name);
+    }
+
+    println!("\n{} images tagged, {} failed{}", tagged, failed, if dry_run { " (dry run, nothing changed)" } else { "" });
+    Ok(())
+}
+
+fn get_images(pdb: &PantsuDB, image_paths: &[PathBuf]) -> AppResult<Vec<ImageInfo>> {
+    if image_paths.is_empty() {
+        // every image without an existing sauce, whether it was not looked up yet, not found or is still unsure.
+        // No image is shown, so images of all ratings are tagged
+        let mut images = pdb.get_images_transaction().with_not_existing_sauce().max_rating(None).execute()?;
+        images.extend(pdb.get_images_transaction().with_not_checked_sauce().max_rating(None).execute()?);
+        images.extend(pdb.get_images_transaction().with_unsure_sauce().max_rating(None).execute()?);
+        return Ok(images);
+    }
+    image_paths.iter()
+        .map(|path| {
+            let image_handle = common::image_handle_from_path(path)?;
+            pdb.get_image_transaction(&image_handle).execute()?
+                .ok_or_else(|| AppError::ImageNotFound(image_handle.get_filename().to_string()))
+        })
+        .collect()
+}
@@ -1,66 +1,69 @@
 use std::collections::HashSet;
 use std::{io, iter};
 use std::path::{PathBuf, Path};
+use std::time::Duration;
 use colored::Colorize;
 use futures::{stream, StreamExt, TryStreamExt};
 use log::{info, warn};
-use pantsu_tags::{ImageHandle, PantsuTag, Sauce, SauceMatch, TmpFile, ImageInfo};
+use pantsu_tags::{ImageHandle, ImageSource, PantsuTag, Sauce, SauceJudgePolicy, SauceJudgement, SauceMatch, SauceProviders, TmpFile, ImageInfo};
 use pantsu_tags::db::PantsuDB;
 use tokio::sync::mpsc::{Receiver, self};
 use tokio::task;
-use crate::{AppError, CONFIGURATION, feh, common};
+use crate::{AppError, CONFIGURATION, sauce_providers, feh, common};
 use crate::common::{AppResult};
 use crate::feh::FehProcesses;
 
-// sauce matches with a higher similarity will be automatically accepted
-const FOUND_SIMILARITY_THRESHOLD: i32 = 90;
-// sauce matches with a higher similarity are relevant. (Others will be discarded)
-const RELEVANT_SIMILARITY_THESHOLD: i32 = 45;
-
-const MAX_CONCURRENT_REQUESTS: usize = 16;
-const MAX_PREFETCH_SOURCE_RESOLUTION: usize = 4;
-
-pub fn auto_lookup_tags(image_paths: Vec<PathBuf>, sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool, no_feh: bool) -> AppResult<()> {
-    let pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
-    let images = get_images(&pdb, &image_paths, sauce_existing, sauce_not_existing, sauce_not_checked)?;
+pub fn auto_lookup_tags(image_paths: Vec<PathBuf>, sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool, recheck_older_than: Option<String>,
+                        no_feh: bool, policy: &SauceJudgePolicy) -> AppResult<()> {
+    let recheck_older_than = recheck_older_than.as_deref().map(common::parse_duration).transpose()?;
+    let pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths, sauce_existing, sauce_not_existing, sauce_not_checked, recheck_older_than)?;
     
     let rt = tokio::runtime::Runtime::new()
         .or_else(|e| Err(pantsu_tags::Error::TokioInitError(e)))?;
-    let (pdb, stats, unsure_source_images) = rt.block_on(auto_lookup_tags_async(pdb, images))?;
+    // rechecks must query the providers again instead of returning the cached sauces of the last lookup
+    let refresh = recheck_older_than.is_some();
+    let (pdb, stats, unsure_source_images) = rt.block_on(auto_lookup_tags_async(pdb, images, refresh, policy))?;
 
-    let stats = rt.block_on(resolve_sauce_unsure(pdb, unsure_source_images, stats, no_feh))?;
+    let stats = rt.block_on(resolve_sauce_unsure(pdb, unsure_source_images, stats, no_feh, policy))?;
     println!();
     stats.print_stats();
     Ok(())
 }
 
 
-async fn auto_lookup_tags_async(pdb: PantsuDB, images: HashSet<ImageInfo>) -> AppResult<(PantsuDB,AutoTaggingStats,Vec<SauceUnsure>)> {
+async fn auto_lookup_tags_async(pdb: PantsuDB, images: HashSet<ImageInfo>, refresh: bool, policy: &SauceJudgePolicy) -> AppResult<(PantsuDB,AutoTaggingStats,Vec<SauceUnsure>)> {
+    let providers = sauce_providers()?;
     let tagging_stats = AutoTaggingStats::new(images.len() as u64);
     let unsure_sauces: Vec<SauceUnsure> = Vec::new();
 
     let res = stream::iter(images)
         .map(|image| async move {
-            let sauces = pantsu_tags::get_image_sauces(&CONFIGURATION.library_path, image.get_image()).await?;
-            let judgement = judge_sauce(image.get_image(), sauces).await?;
-            Ok((image,judgement))
+            let (sauces, cached) = pantsu_tags::lookup_image_sauces(providers, &CONFIGURATION.library_path, image.get_image(), refresh).await?;
+            let judgement = pantsu_tags::judge_sauce(providers, policy, &image, sauces.clone()).await?;
+            Ok((image,sauces,cached,judgement))
         })
-        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
-        .try_fold((pdb,tagging_stats,unsure_sauces), |(mut pdb, mut stats, mut unsures), (image,judgement)| async move {
+        .buffer_unordered(policy.max_concurrent_lookups)
+        .try_fold((pdb,tagging_stats,unsure_sauces), |(mut pdb, mut stats, mut unsures), (image,sauces,cached,judgement)| async move {
+            if !cached { // cached sauces were already recorded by the lookup that cached them
+                pdb.add_sauce_candidates_transaction(image.get_image()).add_candidates(&sauces).judged_by(policy).execute()?; // keep all matches to audit the judgement later
+            }
             store_sauce_in_db(&mut pdb, image.get_image(), &judgement).await?;
             let image_name = image.get_image().get_filename();
             match judgement {
-                SauceJudgement::Matching { sauce: _, tags: _ } => {
+                SauceJudgement::Matching { sauce: _, tags: _, auto_judged } => {
                     stats.success += 1;
+                    stats.auto_success += auto_judged as u64;
                     println!("{} - {}", "Successfully tagged image".green(), image_name);
                 }
-                SauceJudgement::Unsure(unsure) => {
-                    unsures.push(unsure);
+                SauceJudgement::Unsure(matches) => {
+                    unsures.push(SauceUnsure { image_handle: image.get_image().clone(), matches });
                     stats.unsure += 1;
                     println!("{} - {}", "Source could be wrong    ".yellow(), image_name);
                 }
-                SauceJudgement::NotExisting => {
+                SauceJudgement::NotExisting { auto_judged } => {
                     stats.no_source += 1;
+                    stats.auto_no_source += auto_judged as u64;
                     println!("{} - {}", "No source found          ".red(), image_name);
                 }
             }
@@ -70,51 +73,54 @@ async fn auto_lookup_tags_async(pdb: PantsuDB, images: HashSet<ImageInfo>) -> Ap
     res
 }
 
-async fn judge_sauce(image: &ImageHandle, sauces: Vec<SauceMatch>) -> AppResult<SauceJudgement> {
-    let (good_sauces, unsure_sauces): (Vec<SauceMatch>, Vec<SauceMatch>) = sauces.into_iter()
-        .filter(|s| s.similarity > RELEVANT_SIMILARITY_THESHOLD)  // only keep relevant sauces
-        .partition(|s| s.similarity > FOUND_SIMILARITY_THRESHOLD);
-
-    for good_sauce in good_sauces {
-        match pantsu_tags::get_sauce_tags(&good_sauce).await {
-            Ok(tags) => return Ok(SauceJudgement::Matching { sauce: good_sauce, tags }),
-            Err(pantsu_tags::Error::HtmlParseError) => continue,    // Html error can happen if image was deleted on gelbooru, try next sauceMatch
-            Err(e) => return Err(e.into())
-        }
-    }
-
-    if !unsure_sauces.is_empty() {
-        return Ok(SauceJudgement::Unsure(SauceUnsure { image_handle: image.clone(), matches: unsure_sauces }));
-    }
-
-    Ok(SauceJudgement::NotExisting)
-}
-
-async fn store_sauce_in_db(pdb: &mut PantsuDB, image: &ImageHandle, sauce_judgement: &SauceJudgement) -> AppResult<()> {
+pub(crate) async fn store_sauce_in_db(pdb: &mut PantsuDB, image: &ImageHandle, sauce_judgement: &SauceJudgement) -> AppResult<()> {
+    let providers = sauce_providers()?;
     match sauce_judgement {
-        SauceJudgement::Matching { sauce, tags } => {
+        SauceJudgement::Matching { sauce, tags, auto_judged: _ } => {
+            let tag_author = pantsu_tags::get_sauce_tag_author(providers, sauce)?;
+            let sources = get_sauce_sources(providers, &sauce.link).await;
             pdb.update_images_transaction()
                 .for_image(&image)
                 .update_sauce(&Sauce::Match(pantsu_tags::url_from_str(&sauce.link)?))
                 .add_tags(&tags)
+                .tag_author(&tag_author)
+                .add_sources(&sources)
                 .execute()?;
             info!("Set sauce '{}' to image: '{}'", sauce.link.clone(), image.get_filename());
             info!("Added tags {} to image: '{}'", PantsuTag::display_vec(&tags), image.get_filename());
         },
-        SauceJudgement::NotExisting => { // mark in db that there are no sources for this image
+        SauceJudgement::NotExisting { auto_judged: _ } => { // mark in db that there are no sources for this image
             pdb.update_images_transaction()
                 .for_image(&image)
                 .update_sauce(&Sauce::NotExisting)
                 .execute()?;
             warn!("Set sauce '{}' to image: '{}'", "NOT_EXISTING", image.get_filename());
         },
-        SauceJudgement::Unsure(_) => {}, // tags can be added in the sauce resolution
+        SauceJudgement::Unsure(_) => { // tags can be added in the sauce resolution, or later with review-sauces
+            pdb.update_images_transaction()
+                .for_image(image)
+                .update_sauce(&Sauce::Unsure)
+                .execute()?;
+            info!("Set sauce '{}' to image: '{}'", "UNSURE", image.get_filename());
+        },
     }
     Ok(())
 }
 
+// the sources are optional, failing to look them up must not lose the found tags
+pub(crate) async fn get_sauce_sources(providers: &SauceProviders, sauce_link: &str) -> Vec<ImageSource> {
+    match pantsu_tags::get_sauce_sources(providers, sauce_link).await {
+        Ok(sources) => sources,
+        Err(e) => {
+            warn!("Failed to look up the sources of sauce '{}': {}", sauce_link, e);
+            Vec::new()
+        }
+    }
+}
+
 
-async fn resolve_sauce_unsure(pdb: PantsuDB, images_to_resolve: Vec<SauceUnsure>, stats: AutoTaggingStats, no_feh: bool) -> AppResult<AutoTaggingStats> {
+pub(crate) async fn resolve_sauce_unsure(pdb: PantsuDB, images_to_resolve: Vec<SauceUnsure>, stats: AutoTaggingStats, no_feh: bool, policy: &SauceJudgePolicy) -> AppResult<AutoTaggingStats> {
+    let providers = sauce_providers()?;
     if images_to_resolve.is_empty() {
         return Ok(stats);
     }
@@ -128,12 +134,12 @@ async fn resolve_sauce_unsure(pdb: PantsuDB, images_to_resolve: Vec<SauceUnsure>
         .map(|image| {
             let tx = tx.clone();
             async move {
-                let thumbnails = pantsu_tags::get_thumbnails(&image.matches).await;
+                let thumbnails = pantsu_tags::get_thumbnails(providers, &image.matches).await;
                 tx.send((image, thumbnails)).await
                     .or(Err(AppError::TaskCommunicationError))
             }
         })
-        .buffer_unordered(MAX_PREFETCH_SOURCE_RESOLUTION)
+        .buffer_unordered(policy.max_prefetched_reviews)
         .try_for_each(|_| async move { Ok(()) }).await;
 
     drop(tx);
@@ -142,9 +148,10 @@ async fn resolve_sauce_unsure(pdb: PantsuDB, images_to_resolve: Vec<SauceUnsure>
     Ok(stats)
 }
 
-type ResolveRequest = (SauceUnsure, pantsu_tags::Result<Vec<TmpFile>>);
+type ResolveRequest = (SauceUnsure, Vec<pantsu_tags::Result<TmpFile>>);
 
 fn resolve_sauce_thread(mut pdb: PantsuDB, mut rx: Receiver<ResolveRequest>, num_images_to_resolve: usize, mut stats: AutoTaggingStats, use_feh: bool) -> AppResult<AutoTaggingStats> {
+    let providers = sauce_providers()?;
     let rt = tokio::runtime::Runtime::new()
         .or_else(|e| Err(pantsu_tags::Error::TokioInitError(e)))?;
     let mut thumb_displayer = ThumbnailDisplayer::new(use_feh);
@@ -174,11 +181,15 @@ fn resolve_sauce_thread(mut pdb: PantsuDB, mut rx: Receiver<ResolveRequest>, num
                     continue;
                 }
                 let correct_sauce = &image.matches[num-1];
-                let tags = rt.block_on(pantsu_tags::get_sauce_tags(correct_sauce))?;
+                let tags = rt.block_on(pantsu_tags::get_sauce_tags(providers, correct_sauce))?;
+                let tag_author = pantsu_tags::get_sauce_tag_author(providers, correct_sauce)?;
+                let sources = rt.block_on(get_sauce_sources(providers, &correct_sauce.link));
                 pdb.update_images_transaction()
                     .for_image(&image.image_handle)
                     .update_sauce(&Sauce::Match(pantsu_tags::url_from_str(&correct_sauce.link)?))
                     .add_tags(&tags)
+                    .tag_author(&tag_author)
+                    .add_sources(&sources)
                     .execute()?;
                 stats.unsure_success += 1;
                 println!("{}", "Successfully added tags to image".green());
@@ -197,7 +208,7 @@ fn resolve_sauce_thread(mut pdb: PantsuDB, mut rx: Receiver<ResolveRequest>, num
             }
             else if input.eq("s") {
                 stats.skip_unsure();
-                println!("Skip remaining images");
+                println!("Skip remaining images, they can be reviewed later with review-sauces");
                 thumb_displayer.kill_feh();
                 return Ok(stats);
             }
@@ -209,8 +220,11 @@ fn resolve_sauce_thread(mut pdb: PantsuDB, mut rx: Receiver<ResolveRequest>, num
     Ok(stats)
 }
 
-fn get_images(pdb: &PantsuDB, image_paths: &Vec<PathBuf>, sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool) -> AppResult<HashSet<ImageInfo>> {
-    let mut images: HashSet<ImageInfo> = if sauce_existing {
+fn get_images(pdb: &PantsuDB, image_paths: &Vec<PathBuf>, sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool,
+              recheck_older_than: Option<Duration>) -> AppResult<HashSet<ImageInfo>> {
+    let mut images: HashSet<ImageInfo> = if let Some(min_age) = recheck_older_than {
+        pdb.get_images_transaction().with_not_existing_sauce().recheck_due(min_age).execute()?
+    } else if sauce_existing {
         pdb.get_images_transaction().with_existing_sauce().execute()?
     } else if sauce_not_existing {
         pdb.get_images_transaction().with_not_existing_sauce().execute()?
@@ -227,7 +241,7 @@ fn get_images(pdb: &PantsuDB, image_paths: &Vec<PathBuf>, sauce_existing: bool,
     Ok(images)
 }
 
-struct AutoTaggingStats {
+pub(crate) struct AutoTaggingStats {
     total: u64,
     success: u64,
     no_source: u64,
@@ -235,23 +249,37 @@ struct AutoTaggingStats {
     unsure_success: u64,
     unsure_no_source: u64,
     unsure_skip: u64,
+    // unsure sauces that were judged by comparing them with the local image
+    auto_success: u64,
+    auto_no_source: u64,
 }
 impl AutoTaggingStats {
     fn new(total_images: u64) -> AutoTaggingStats {
-        AutoTaggingStats { total:total_images, success: 0, no_source: 0, unsure: 0, unsure_success: 0, unsure_no_source: 0, unsure_skip: 0 }
+        AutoTaggingStats { total:total_images, success: 0, no_source: 0, unsure: 0, unsure_success: 0, unsure_no_source: 0, unsure_skip: 0, auto_success: 0, auto_no_source: 0 }
+    }
+
+    // all reviewed images are unsure ones
+    pub(crate) fn for_review(unsure_images: u64) -> AutoTaggingStats {
+        AutoTaggingStats { unsure: unsure_images, ..AutoTaggingStats::new(unsure_images) }
     }
 
     fn skip_unsure(&mut self) {
         self.unsure_skip = self.unsure - self.unsure_success - self.unsure_no_source
     }
 
-    fn print_stats(&self) {
+    pub(crate) fn print_stats(&self) {
         const TEXT_WIDTH: usize = 17;
         const NUM_WIDTH: usize = 5;
         println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "Total images:", self.total);
         println!("{:->width$}", "", width = TEXT_WIDTH + NUM_WIDTH);
         println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "Source found:", self.success);
+        if self.auto_success > 0 {
+            println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "  Auto accepted:", self.auto_success);
+        }
         println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "Source not found:", self.no_source);
+        if self.auto_no_source > 0 {
+            println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "  Auto rejected:", self.auto_no_source);
+        }
         println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "Source unsure:", self.unsure);
         if self.unsure > 0 {
             println!("{:<TEXT_WIDTH$}{:>NUM_WIDTH$}", "  Source correct:", self.unsure_success);
@@ -261,18 +289,17 @@ impl AutoTaggingStats {
             }
         }
 
+        let auto_judged = self.auto_success + self.auto_no_source;
+        if auto_judged > 0 {
+            println!("\n{} of {} unsure sources were judged automatically, {} needed a human", auto_judged, auto_judged + self.unsure, self.unsure);
+        }
+
         assert_eq!(self.total, self.success + self.no_source + self.unsure);
         assert_eq!(self.unsure, self.unsure_success + self.unsure_no_source + self.unsure_skip);
     }
 }
 
-enum SauceJudgement {
-    Matching { sauce: SauceMatch, tags: Vec<PantsuTag> },
-    Unsure (SauceUnsure),
-    NotExisting,
-}
-
-struct SauceUnsure {
+pub(crate) struct SauceUnsure {
     pub image_handle: ImageHandle,
     pub matches: Vec<SauceMatch>,
 }
@@ -291,7 +318,7 @@ impl ThumbnailDisplayer {
         }
     }
 
-    fn feh_display(&mut self, image_path: &Path, thumbnails: pantsu_tags::Result<Vec<TmpFile>>) {
+    fn feh_display(&mut self, image_path: &Path, thumbnails: Vec<pantsu_tags::Result<TmpFile>>) {
         if !self.enabled {
             return;
         }
@@ -299,9 +326,25 @@ impl ThumbnailDisplayer {
             Ok(path) => path,
             Err(_) => { self.print_skip("Image path is invalid"); return; },
         };
-        match thumbnails {
-            Ok(thumbnails) => self.thumbnails = thumbnails, // take ownership to guarantee TmpFile exists as long as feh runs.
-            Err(_) => { self.print_skip("Unable to download source thumbnails"); return; },
+        // the numbers of the sources without a thumbnail, so the shown thumbnails can still be told apart
+        let mut missing = Vec::new();
+        self.thumbnails = thumbnails.into_iter().enumerate() // take ownership to guarantee TmpFile exists as long as feh runs.
+            .filter_map(|(index, thumbnail)| match thumbnail {
+                Ok(thumbnail) => Some(thumbnail),
+                Err(e) => {
+                    warn!("Unable to download thumbnail of source {}: {}", index + 1, e);
+                    missing.push((index + 1).to_string());
+                    None
+                },
+            })
+            .collect();
+        if self.thumbnails.is_empty() {
+            self.print_skip("Unable to download source thumbnails");
+            return;
+        }
+        let label = match missing.is_empty() {
+            true => String::from("Potential source"),
+            false => format!("Potential source (no thumbnail of {})", missing.join(", ")),
         };
 
         let paths = self.thumbnails.iter()
@@ -313,7 +356,7 @@ impl ThumbnailDisplayer {
         };
         let mut procs = self.feh_processes.take().unwrap_or(FehProcesses::new_empty());
         procs = feh::feh_display_images(iter::once(image_path_str.as_str()), "Local image", procs);
-        self.feh_processes = Some(feh::feh_display_images(paths.into_iter(), "Potential source", procs));
+        self.feh_processes = Some(feh::feh_display_images(paths.into_iter(), &label, procs));
     }
 
     fn print_skip(&mut self, msg: &str) {
@@ -0,0 +1,11 @@
+use log::info;
+use crate::common::AppResult;
+use crate::sauce_providers;
+
+pub fn clear_cache() -> AppResult<()> {
+    let providers = sauce_providers()?;
+    let removed = pantsu_tags::clear_sauce_cache(providers)?;
+    info!("Removed {} cached lookups", removed);
+    println!("Removed {} cached lookups", removed);
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use colored::Colorize;
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use pantsu_tags::{ImageInfo, Sauce, SauceJudgePolicy, SauceJudgement, SauceLiveness, SauceMatch};
+use pantsu_tags::db::PantsuDB;
+use crate::common::{AppResult, AppError};
+use crate::{common, CONFIGURATION, sauce_providers};
+use super::store_sauce_in_db;
+
+struct DeadSauce {
+    image: ImageInfo,
+    // stored candidates of earlier lookups that aren't known to be dead
+    alternatives: Vec<SauceMatch>,
+    dead_links: HashSet<String>,
+}
+
+pub fn check_sauces(image_paths: Vec<PathBuf>, fallback: bool, policy: &SauceJudgePolicy) -> AppResult<()> {
+    let providers = sauce_providers()?;
+    let mut pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths)?
+        .into_iter()
+        .filter_map(|image| match image.get_sauce() {
+            Sauce::Match(url) => {
+                let link = url.to_string();
+                Some((image, link))
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        println!("No images with a sauce to check");
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(pantsu_tags::Error::TokioInitError)?;
+    let results = rt.block_on(stream::iter(images)
+        .map(|(image, link)| async move {
+            let liveness = pantsu_tags::check_sauce_liveness(providers, &link).await;
+            (image, link, liveness)
+        })
+        .buffer_unordered(policy.max_concurrent_lookups)
+        .collect::<Vec<_>>());
+
+    let (mut alive, mut failed) = (0, 0);
+    let mut dead_sauces = Vec::new();
+    for (image, link, liveness) in results {
+        let image_name = image.get_image().get_filename();
+        match liveness {
+            Ok(SauceLiveness::Alive) => {
+                alive += 1;
+                pdb.update_dead_sauces_transaction(image.get_image()).mark_alive(&link).execute()?;
+            },
+            Ok(SauceLiveness::Dead) => {
+                println!("{} - {}: {}", "Sauce is dead".red(), image_name, link);
+                warn!("Sauce '{}' of image '{}' was deleted or moved", link, image_name);
+                pdb.update_dead_sauces_transaction(image.get_image()).mark_dead(&link).execute()?;
+                let dead_links: HashSet<String> = pdb.get_dead_sauces_transaction(image.get_image()).execute()?.into_iter().collect();
+                let alternatives = get_alternatives(&pdb, &image, &dead_links)?;
+                dead_sauces.push(DeadSauce { image, alternatives, dead_links });
+            },
+            Err(e) => {
+                failed += 1;
+                println!("{} - {}: {}", "Failed to check sauce".yellow(), image_name, e);
+                warn!("Failed to check sauce '{}' of image '{}': {}", link, image_name, e);
+            },
+        }
+    }
+    println!("\n{} sauces alive, {} dead, {} failed to check", alive, dead_sauces.len(), failed);
+    if !fallback || dead_sauces.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nLooking for new sauces of {} images", dead_sauces.len());
+    let replacements = rt.block_on(stream::iter(dead_sauces)
+        .map(|dead_sauce| async move {
+            let replacement = find_replacement(&dead_sauce, policy).await;
+            (dead_sauce.image, replacement)
+        })
+        .buffer_unordered(policy.max_concurrent_lookups)
+        .collect::<Vec<_>>());
+
+    let (mut replaced, mut unsure, mut not_existing) = (0, 0, 0);
+    for (image, replacement) in replacements {
+        let image_name = image.get_image().get_filename();
+        let (judgement, fresh_sauces) = match replacement {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                println!("{} - {}: {}", "Failed to find new sauce".yellow(), image_name, e);
+                warn!("Failed to find new sauce of image '{}': {}", image_name, e);
+                continue;
+            },
+        };
+        if let Some(sauces) = fresh_sauces {
+            pdb.add_sauce_candidates_transaction(image.get_image()).add_candidates(&sauces).judged_by(policy).execute()?;
+        }
+        rt.block_on(store_sauce_in_db(&mut pdb, image.get_image(), &judgement))?;
+        match judgement {
+            SauceJudgement::Matching { sauce, .. } => {
+                replaced += 1;
+                println!("{} - {}: {}", "Found new sauce".green(), image_name, sauce.link);
+            },
+            SauceJudgement::Unsure(_) => {
+                unsure += 1;
+                println!("{} - {}", "New sauce could be wrong".yellow(), image_name);
+            },
+            SauceJudgement::NotExisting { .. } => {
+                not_existing += 1;
+                println!("{} - {}", "No new sauce found".red(), image_name);
+            },
+        }
+    }
+    println!("\n{} sauces replaced, {} unsure (see review-sauces), {} without sauce", replaced, unsure, not_existing);
+    Ok(())
+}
+
+// Judges the stored alternatives first and only looks the image up again if none of them matches.
+// The cache is bypassed, as it may still hold the dead sauce or the tags of other deleted posts.
+// Fresh lookup results are returned, so they can be recorded like any other lookup
+async fn find_replacement(dead_sauce: &DeadSauce, policy: &SauceJudgePolicy) -> AppResult<(SauceJudgement, Option<Vec<SauceMatch>>)> {
+    let providers = sauce_providers()?;
+    let image = &dead_sauce.image;
+    if !dead_sauce.alternatives.is_empty() {
+        let judgement = pantsu_tags::judge_sauce_uncached(providers, policy, image, dead_sauce.alternatives.clone()).await?;
+        if let SauceJudgement::Matching { .. } = judgement {
+            info!("Replacing dead sauce of image '{}' with a stored candidate", image.get_image().get_filename());
+            return Ok((judgement, None));
+        }
+    }
+    let (sauces, _) = pantsu_tags::lookup_image_sauces(providers, &CONFIGURATION.library_path, image.get_image(), true).await?;
+    let alive_sauces = sauces.iter()
+        .filter(|sauce| !dead_sauce.dead_links.contains(&sauce.link))
+        .cloned()
+        .collect();
+    let judgement = pantsu_tags::judge_sauce_uncached(providers, policy, image, alive_sauces).await?;
+    Ok((judgement, Some(sauces)))
+}
+
+fn get_alternatives(pdb: &PantsuDB, image: &ImageInfo, dead_links: &HashSet<String>) -> AppResult<Vec<SauceMatch>> {
+    let mut seen_links = HashSet::new();
+    Ok(pdb.get_sauce_candidates_transaction(image.get_image())
+        .execute()?
+        .into_iter()
+        .filter(|candidate| !dead_links.contains(&candidate.link) && seen_links.insert(candidate.link.clone()))
+        .map(|candidate| candidate.to_sauce_match())
+        .collect())
+}
+
+fn get_images(pdb: &PantsuDB, image_paths: &[PathBuf]) -> AppResult<Vec<ImageInfo>> {
+    if image_paths.is_empty() {
+        // no image is shown, so the sauces of all ratings are checked
+        return Ok(pdb.get_images_transaction().with_existing_sauce().max_rating(None).execute()?);
+    }
+    image_paths.iter()
+        .map(|path| {
+            let image_handle = common::image_handle_from_path(path)?;
+            pdb.get_image_transaction(&image_handle).execute()?
+                .ok_or_else(|| AppError::ImageNotFound(image_handle.get_filename().to_string()))
+        })
+        .collect()
+}
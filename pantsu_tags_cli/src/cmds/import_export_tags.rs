@@ -1,17 +1,16 @@
 use std::path::Path;
 
-use pantsu_tags::db::PantsuDB;
 
 use crate::{common::AppResult, CONFIGURATION};
 
 pub fn import_tags(path: &Path) -> AppResult<()> {
-    let mut pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let mut pdb = CONFIGURATION.open_db()?;
     pdb.import_tags(path)?;
     Ok(())
 }
 
 pub fn export_tags(path: &Path) -> AppResult<()> {
-    let mut pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let mut pdb = CONFIGURATION.open_db()?;
     pdb.export_tags(path)?;
     Ok(())
 }
\ No newline at end of file
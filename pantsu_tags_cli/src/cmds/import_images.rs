@@ -13,7 +13,7 @@ use crate::feh::FehProcesses;
 pub fn import_images(no_feh: bool, images: Vec<PathBuf>, always_copy_images: bool) -> AppResult<()> {
     let mut import_stats = ImportStats::default();
     let mut valid_images: Vec<ImageToImport> = Vec::new();
-    let mut pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let mut pdb = CONFIGURATION.open_db()?;
 
     for image in &images {
         let image_name = common::get_path(image);
@@ -33,7 +33,8 @@ pub fn import_images(no_feh: bool, images: Vec<PathBuf>, always_copy_images: boo
         }
     }
 
-    let images_in_db = pdb.get_images_transaction().execute()?;
+    // similar images of all ratings, so none of them is imported twice
+    let images_in_db = pdb.get_images_transaction().max_rating(None).execute()?;
     let image_groups = image_similarity::group_similar_images(&valid_images, &images_in_db)?;
 
     let mut image_groups_with_similars: Vec<SimilarImagesGroup> = Vec::new();
@@ -1,27 +1,23 @@
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
-use pantsu_tags::db::{AspectRatio, PantsuDB};
-use pantsu_tags::{Error, PantsuTag, ImageInfo};
+use pantsu_tags::db::AspectRatio;
+use pantsu_tags::{Error, ImageInfo, Rating};
 use crate::common::{AppResult};
 use crate::{common, CONFIGURATION};
 
-pub fn list_images(included_tags: &Vec<String>, excluded_tags: &Vec<String>, ratio: AspectRatio, do_print_filenames: bool,
-                   sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool, sort_order: Vec<String>,
-                   temp_dir: Option<PathBuf>) -> AppResult<()> {
-    let pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+pub fn list_images(included_tags: &[String], excluded_tags: &[String], ratio: AspectRatio, do_print_filenames: bool,
+                   sauce_existing: bool, sauce_not_existing: bool, sauce_not_checked: bool, sauce_unsure: bool, sort_order: Vec<String>,
+                   max_rating: Option<Rating>, temp_dir: Option<PathBuf>) -> AppResult<()> {
+    let pdb = CONFIGURATION.open_db()?;
 
     let sort_order = common::parse_image_sort_order(sort_order)?;
     
-    let included_tags = included_tags.into_iter()
-        .map(|t| PantsuTag::from_str(t).or_else(|_| Ok(PantsuTag::new(t.to_string(), pantsu_tags::PantsuTagType::General))))
-        .collect::<AppResult<Vec<PantsuTag>>>()?;
-    let excluded_tags = excluded_tags.into_iter()
-        .map(|t| PantsuTag::from_str(t).or_else(|_| Ok(PantsuTag::new(t.to_string(), pantsu_tags::PantsuTagType::General))))
-        .collect::<AppResult<Vec<PantsuTag>>>()?;
+    let included_tags = common::parse_query_tags(&pdb, included_tags)?;
+    let excluded_tags = common::parse_query_tags(&pdb, excluded_tags)?;
     let images_transaction = pdb.get_images_transaction()
         .including_tags(&included_tags)
         .excluding_tags(&excluded_tags)
-        .with_ratio(ratio);
+        .with_ratio(ratio)
+        .max_rating(max_rating);
 
     let images_transaction = if sauce_existing {
         images_transaction.with_existing_sauce()
@@ -29,6 +25,8 @@ pub fn list_images(included_tags: &Vec<String>, excluded_tags: &Vec<String>, rat
         images_transaction.with_not_existing_sauce()
     } else if sauce_not_checked {
         images_transaction.with_not_checked_sauce()
+    } else if sauce_unsure {
+        images_transaction.with_unsure_sauce()
     } else {
         images_transaction
     };
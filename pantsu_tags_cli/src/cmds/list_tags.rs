@@ -2,14 +2,15 @@ use std::path::{PathBuf};
 
 use colored::Colorize;
 
-use pantsu_tags::{Error, PantsuTagType};
-use pantsu_tags::db::PantsuDB;
+use pantsu_tags::{Error, PantsuTag, PantsuTagType};
 
 use crate::common::{AppResult, self};
 use crate::CONFIGURATION;
 
-pub fn list_tags(images: Vec<PathBuf>, tag_types: Vec<PantsuTagType>, sort_order: Vec<String>, do_print_tagnames: bool) -> AppResult<()> {
-    if images.len() == 0 {
+pub fn list_tags(images: Vec<PathBuf>, tag_types: Vec<PantsuTagType>, sort_order: Vec<String>, do_print_tagnames: bool, do_print_counts: bool) -> AppResult<()> {
+    if do_print_counts {
+        list_tag_counts(tag_types, do_print_tagnames)?;
+    } else if images.len() == 0 {
         list_all_tags(tag_types, do_print_tagnames)?;
     } else {
         list_tags_for_images(images, tag_types, sort_order, do_print_tagnames)?;
@@ -18,15 +19,16 @@ pub fn list_tags(images: Vec<PathBuf>, tag_types: Vec<PantsuTagType>, sort_order
 }
 
 fn list_tags_for_images(images: Vec<PathBuf>, tag_types: Vec<PantsuTagType>, sort_order: Vec<String>, do_print_tagnames: bool) -> AppResult<()> {
-    let db = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let db = CONFIGURATION.open_db()?;
     let len = images.len();
     let sort_order = common::parse_tag_sort_order(sort_order)?;
 
     for (i, path) in images.into_iter().enumerate() {
         let image = common::image_handle_from_path(&path)?;
-        let _ = db.get_image_transaction(&image)
+        let image = db.get_image_transaction(&image)
             .execute()?
-            .ok_or_else(|| Error::ImageNotFoundInDB(image.get_filename().to_string()))?;
+            .ok_or_else(|| Error::ImageNotFoundInDB(image.get_filename().to_string()))?
+            .get_image().clone();
         let transaction = db.get_image_tags_transaction(&image)
             .with_types(&tag_types);
         let tags = match &sort_order {
@@ -48,7 +50,7 @@ fn list_tags_for_images(images: Vec<PathBuf>, tag_types: Vec<PantsuTagType>, sor
 }
 
 fn list_all_tags(tag_types: Vec<PantsuTagType>, do_print_tagnames: bool) -> AppResult<()> {
-    let db = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let db = CONFIGURATION.open_db()?;
     let tags = db.get_tags_transaction()
         .with_types(&tag_types)
         .execute()?;
@@ -58,3 +60,53 @@ fn list_all_tags(tag_types: Vec<PantsuTagType>, do_print_tagnames: bool) -> AppR
     Ok(())
 }
 
+
+fn list_tag_counts(tag_types: Vec<PantsuTagType>, do_print_tagnames: bool) -> AppResult<()> {
+    let db = CONFIGURATION.open_db()?;
+    let mut tag_counts = db.get_tag_counts_transaction()
+        .with_types(&tag_types)
+        .execute()?;
+    tag_counts.sort_by_key(|tag_count| std::cmp::Reverse(tag_count.count));
+    for tag_count in tag_counts {
+        let tag = if do_print_tagnames { tag_count.tag.tag_name } else { tag_count.tag.to_string() };
+        println!("{:>6}  {}", tag_count.count, tag);
+    }
+    Ok(())
+}
+
+pub fn related_tags(tags: Vec<String>, tag_types: Vec<PantsuTagType>, limit: usize) -> AppResult<()> {
+    let db = CONFIGURATION.open_db()?;
+    let tags = common::parse_query_tags(&db, &tags)?;
+    let related_tags = db.get_related_tags_transaction()
+        .for_tags(&tags)
+        .with_types(&tag_types)
+        .limit(limit)
+        .execute()?;
+    if related_tags.is_empty() {
+        println!("No tags are used together with {}", PantsuTag::display_vec(&tags));
+        return Ok(());
+    }
+    for related in related_tags {
+        println!("{:>6}  {} ({:.0}% of the images, used on {} images in total)",
+            related.cooccurrences, related.tag, related.frequency * 100.0, related.count);
+    }
+    Ok(())
+}
+
+pub fn search_tags(pattern: &str, tag_types: Vec<PantsuTagType>, do_print_tagnames: bool) -> AppResult<()> {
+    let db = CONFIGURATION.open_db()?;
+    let transaction = db.get_tags_transaction()
+        .with_types(&tag_types);
+    let is_glob = pattern.contains(['*', '?', '[']);
+    let tags = match is_glob {
+        true => transaction.matching(pattern).execute()?,
+        false => transaction.with_prefix(pattern).execute()?,
+    };
+    if tags.is_empty() && !is_glob {
+        return common::warn_unknown_tag(&db, pattern);
+    }
+    for tag in tags {
+        println!("{}", if do_print_tagnames { tag.tag_name } else { tag.to_string() } )
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use std::path::Path;
+use log::{info, warn};
+use pantsu_tags::Error;
+use crate::common::{AppResult, self};
+use crate::CONFIGURATION;
+
+pub fn merge_images(keep: &Path, discard: &Path, keep_alias: bool) -> AppResult<()> {
+    let mut pdb = CONFIGURATION.open_db()?;
+    let keep = common::image_handle_from_path(keep)?;
+    let discard = common::image_handle_from_path(discard)?;
+    let transaction = pdb.merge_images(&keep, &discard);
+    let transaction = if keep_alias { transaction.keep_alias() } else { transaction };
+    let sauce = transaction.execute()?;
+    info!("Merged image '{}' into image '{}' with sauce '{}'", discard.get_filename(), keep.get_filename(), sauce);
+
+    let path = discard.get_path(CONFIGURATION.library_path.as_path());
+    if path.is_file() {
+        std::fs::remove_file(path)
+            .map_err(|e| Error::FileNotFound(e, discard.get_filename().to_string()))?;
+        info!("Removed image: '{}'", discard.get_filename());
+    } else {
+        println!("Merged image is not in the library: '{}'", discard.get_filename());
+        warn!("Merged image is not in the library: '{}'", discard.get_filename());
+    }
+    println!("Merged '{}' into '{}'", discard.get_filename(), keep.get_filename());
+    Ok(())
+}
@@ -23,4 +23,36 @@ mod auto_lookup_tags;
 pub use auto_lookup_tags::*;
 
 mod import_export_tags;
-pub use import_export_tags::*;
\ No newline at end of file
+pub use import_export_tags::*;
+
+mod merge_images;
+pub use merge_images::*;
+
+mod cache;
+pub use cache::*;
+
+mod review_sauces;
+pub use review_sauces::*;
+
+mod refresh_tags;
+pub use refresh_tags::*;
+
+mod check_sauces;
+pub use check_sauces::*;
+
+mod upgrade_images;
+pub use upgrade_images::*;
+
+mod set_clear_sauce;
+pub use set_clear_sauce::*;
+
+mod suggest_tags;
+pub use suggest_tags::*;
+
+mod normalize_tags;
+pub use normalize_tags::*;
+
+#[cfg(feature = "ai-tagger")]
+mod ai_tag;
+#[cfg(feature = "ai-tagger")]
+pub use ai_tag::*;
@@ -0,0 +1,18 @@
+use colored::Colorize;
+use log::{info, warn};
+use crate::common::AppResult;
+use crate::CONFIGURATION;
+
+pub fn normalize_tags() -> AppResult<()> {
+    let mut db = CONFIGURATION.open_db()?;
+    let (renamed, collisions) = db.normalize_tags(&CONFIGURATION.tag_normalizer)?;
+    for collision in &collisions {
+        warn!("Kept tag '{}' of image '{}', it already has a tag of another type named '{}'",
+            collision.tag, collision.filename, collision.normalized_name);
+        eprintln!("{} kept tag '{}' of '{}', it already has a tag of another type named '{}'",
+            "Warning:".yellow(), collision.tag, collision.filename, collision.normalized_name);
+    }
+    info!("Normalized {} tags", renamed);
+    println!("Normalized {} tags", renamed);
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use colored::Colorize;
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use pantsu_tags::{ImageInfo, PantsuTag, PantsuTagAuthor, PantsuTagDiff, Sauce};
+use pantsu_tags::db::PantsuDB;
+use crate::common::{AppResult, AppError};
+use crate::{common, CONFIGURATION, sauce_providers};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+struct TagRefresh {
+    image: ImageInfo,
+    sauce_link: String,
+    tag_author: PantsuTagAuthor,
+    stored_tags: Vec<PantsuTag>,
+}
+
+pub fn refresh_tags(image_paths: Vec<PathBuf>, included_tags: &[String], excluded_tags: &[String], older_than_days: Option<u64>, dry_run: bool) -> AppResult<()> {
+    let providers = sauce_providers()?;
+    let mut pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths, included_tags, excluded_tags, older_than_days)?;
+
+    // only the tags found on the sauce are compared, tags of the user or of other sauces stay untouched
+    let mut refreshs = Vec::new();
+    for image in images {
+        let sauce_link = match image.get_sauce() {
+            Sauce::Match(url) => url.to_string(),
+            _ => continue,
+        };
+        let tag_author = providers.tag_author(&sauce_link)?;
+        let stored_tags = pdb.get_image_tags_transaction(image.get_image())
+            .with_sauce(&sauce_link)
+            .execute()?
+            .into_iter()
+            .map(|t| t.tag)
+            .collect();
+        refreshs.push(TagRefresh { image, sauce_link, tag_author, stored_tags });
+    }
+    if refreshs.is_empty() {
+        println!("No images with a sauce to refresh");
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(pantsu_tags::Error::TokioInitError)?;
+    let diffs = rt.block_on(stream::iter(refreshs)
+        .map(|refresh| async move {
+            let diff = pantsu_tags::get_sauce_tags_diff(providers, &refresh.sauce_link, &refresh.stored_tags).await;
+            (refresh, diff)
+        })
+        .buffer_unordered(CONFIGURATION.sauce_judge.max_concurrent_lookups)
+        .collect::<Vec<_>>());
+
+    let (mut changed, mut failed) = (0, 0);
+    for (refresh, diff) in diffs {
+        let image_name = refresh.image.get_image().get_filename();
+        let diff = match diff {
+            Ok(diff) => diff,
+            Err(e) => {
+                failed += 1;
+                println!("{} - {}: {}", "Failed to refresh tags".red(), image_name, e);
+                warn!("Failed to refresh tags of image '{}' from sauce '{}': {}", image_name, refresh.sauce_link, e);
+                continue;
+            }
+        };
+        if diff.is_empty() {
+            continue;
+        }
+        changed += 1;
+        print_diff(image_name, &diff);
+        if dry_run {
+            continue;
+        }
+        pdb.update_images_transaction()
+            .for_image(refresh.image.get_image())
+            .remove_tags(&diff.removed)
+            .add_tags(&diff.added)
+            .tag_author(&refresh.tag_author)
+            .tag_sauce(&refresh.sauce_link)
+            .execute()?;
+        info!("Refreshed tags of image '{}': added {}, removed {}", image_name, PantsuTag::display_vec(&diff.added), PantsuTag::display_vec(&diff.removed));
+    }
+
+    println!("\n{} images with changed tags, {} failed{}", changed, failed, if dry_run { " (dry run, nothing changed)" } else { "" });
+    Ok(())
+}
+
+fn print_diff(image_name: &str, diff: &PantsuTagDiff) {
+    println!("{}", image_name);
+    for tag in &diff.added {
+        println!("  {}", format!("+ {}", tag).green());
+    }
+    for tag in &diff.removed {
+        println!("  {}", format!("- {}", tag).red());
+    }
+}
+
+fn get_images(pdb: &PantsuDB, image_paths: &[PathBuf], included_tags: &[String], excluded_tags: &[String], older_than_days: Option<u64>) -> AppResult<Vec<ImageInfo>> {
+    if !image_paths.is_empty() {
+        return image_paths.iter()
+            .map(|path| {
+                let image_handle = common::image_handle_from_path(path)?;
+                pdb.get_image_transaction(&image_handle).execute()?
+                    .ok_or_else(|| AppError::ImageNotFound(image_handle.get_filename().to_string()))
+            })
+            .collect();
+    }
+    let included_tags = included_tags.iter()
+        .map(|t| PantsuTag::from_str(t))
+        .collect::<pantsu_tags::Result<Vec<PantsuTag>>>()?;
+    let excluded_tags = excluded_tags.iter()
+        .map(|t| PantsuTag::from_str(t))
+        .collect::<pantsu_tags::Result<Vec<PantsuTag>>>()?;
+    let transaction = pdb.get_images_transaction()
+        .including_tags(&included_tags)
+        .excluding_tags(&excluded_tags)
+        .with_existing_sauce();
+    let transaction = match older_than_days {
+        Some(days) => transaction.not_modified_for(Duration::from_secs(days.saturating_mul(SECONDS_PER_DAY))),
+        None => transaction,
+    };
+    Ok(transaction.execute()?)
+}
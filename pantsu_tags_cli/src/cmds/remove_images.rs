@@ -1,12 +1,11 @@
 use std::path::{PathBuf};
 use log::{info, warn};
-use pantsu_tags::db::PantsuDB;
 use pantsu_tags::{Error, ImageHandle};
 use crate::common::{AppResult, self};
 use crate::CONFIGURATION;
 
 pub fn remove_images(images: Vec<PathBuf>) -> AppResult<()> {
-    let mut pdb = PantsuDB::new(CONFIGURATION.database_path.as_path())?;
+    let mut pdb = CONFIGURATION.open_db()?;
     let images = images.into_iter()
         .map(|i| common::image_handle_from_path(&i))
         .collect::<AppResult<Vec<ImageHandle>>>()?;
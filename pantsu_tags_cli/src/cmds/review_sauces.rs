@@ -0,0 +1,33 @@
+use pantsu_tags::{SauceJudgePolicy, SauceMatch};
+use crate::common::AppResult;
+use crate::CONFIGURATION;
+use super::auto_lookup_tags::{self, AutoTaggingStats, SauceUnsure};
+
+pub fn review_sauces(no_feh: bool, policy: &SauceJudgePolicy) -> AppResult<()> {
+    let pdb = CONFIGURATION.open_db()?;
+    let images = pdb.get_images_transaction().with_unsure_sauce().execute()?;
+    if images.is_empty() {
+        println!("No images with unsure sauces to review");
+        return Ok(());
+    }
+
+    let mut unsure_images = Vec::new();
+    for image in images {
+        let matches = pdb.get_sauce_candidates_transaction(image.get_image())
+            .latest_lookup_only()
+            .execute()?
+            .into_iter()
+            .map(|c| c.to_sauce_match())
+            .collect::<Vec<SauceMatch>>();
+        let matches = policy.relevant_sauces(matches);
+        unsure_images.push(SauceUnsure { image_handle: image.get_image().clone(), matches });
+    }
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(pantsu_tags::Error::TokioInitError)?;
+    let stats = AutoTaggingStats::for_review(unsure_images.len() as u64);
+    let stats = rt.block_on(auto_lookup_tags::resolve_sauce_unsure(pdb, unsure_images, stats, no_feh, policy))?;
+    println!();
+    stats.print_stats();
+    Ok(())
+}
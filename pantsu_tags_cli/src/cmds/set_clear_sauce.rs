@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use log::info;
+use pantsu_tags::db::PantsuDB;
+use pantsu_tags::{Error, ImageInfo, PantsuTag, Sauce, SauceMatch};
+use crate::common::{AppResult, self};
+use crate::{CONFIGURATION, sauce_providers};
+use super::get_sauce_sources;
+
+pub fn set_sauce(image: &Path, url: &str, remove_tags: bool, keep_tags: bool) -> AppResult<()> {
+    let providers = sauce_providers()?;
+    let mut pdb = CONFIGURATION.open_db()?;
+    let image = get_image_info(&pdb, image)?;
+    let sauce = Sauce::Match(pantsu_tags::url_from_str(url)?);
+    let stale_sauce = match image.get_sauce() == &sauce {
+        true => None,
+        false => get_stale_sauce(&pdb, &image, remove_tags, keep_tags)?,
+    };
+    let sauce_match = SauceMatch {
+        link: url.to_string(),
+        similarity: 100,    // the user knows the sauce is right
+        resolution: (0, 0),
+        provider: String::from("user"),
+    };
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(pantsu_tags::Error::TokioInitError)?;
+    let tags = rt.block_on(pantsu_tags::get_sauce_tags(providers, &sauce_match))?;
+    let tag_author = pantsu_tags::get_sauce_tag_author(providers, &sauce_match)?;
+    let sources = rt.block_on(get_sauce_sources(providers, url));
+    let transaction = pdb.update_images_transaction()
+        .for_image(image.get_image())
+        .update_sauce(&sauce)
+        .add_tags(&tags)
+        .tag_author(&tag_author)
+        .add_sources(&sources);
+    match &stale_sauce {
+        Some(stale_sauce) => transaction.remove_sauce_tags(stale_sauce).execute(),
+        None => transaction.execute(),
+    }?;
+    let image_name = image.get_image().get_filename();
+    info!("Set sauce '{}' to image: '{}'", url, image_name);
+    if let Some(stale_sauce) = &stale_sauce {
+        info!("Removed tags of sauce '{}' from image: '{}'", stale_sauce, image_name);
+    }
+    info!("Added tags {} to image: '{}'", PantsuTag::display_vec(&tags), image_name);
+    println!("Set sauce of '{}' and added {} tags", image_name, tags.len());
+    Ok(())
+}
+
+pub fn clear_sauce(images: Vec<PathBuf>, remove_tags: bool, keep_tags: bool) -> AppResult<()> {
+    let mut pdb = CONFIGURATION.open_db()?;
+    for image in images {
+        let image = get_image_info(&pdb, &image)?;
+        // tags of the user stay, only the tags found on the cleared sauce are removed
+        let stale_sauce = get_stale_sauce(&pdb, &image, remove_tags, keep_tags)?;
+        let transaction = pdb.update_images_transaction()
+            .for_image(image.get_image())
+            .update_sauce(&Sauce::NotChecked);
+        match &stale_sauce {
+            Some(stale_sauce) => transaction.remove_sauce_tags(stale_sauce).execute(),
+            None => transaction.execute(),
+        }?;
+        let image_name = image.get_image().get_filename();
+        info!("Cleared sauce of image: '{}'", image_name);
+        match stale_sauce {
+            Some(stale_sauce) => {
+                info!("Removed tags of sauce '{}' from image: '{}'", stale_sauce, image_name);
+                println!("Cleared sauce of '{}' and removed its tags", image_name);
+            },
+            None => println!("Cleared sauce of '{}'", image_name),
+        }
+    }
+    Ok(())
+}
+
+fn get_image_info(pdb: &PantsuDB, image: &Path) -> AppResult<ImageInfo> {
+    let image = common::image_handle_from_path(image)?;
+    Ok(pdb.get_image_transaction(&image)
+        .execute()?
+        .ok_or_else(|| Error::ImageNotFoundInDB(image.get_filename().to_string()))?)
+}
+
+// returns the current sauce link if its tags should be removed, asks the user unless told by the flags
+fn get_stale_sauce(pdb: &PantsuDB, image: &ImageInfo, remove_tags: bool, keep_tags: bool) -> AppResult<Option<String>> {
+    let sauce_link = match image.get_sauce() {
+        Sauce::Match(url) => url.to_string(),
+        _ => return Ok(None),
+    };
+    let stale_tags = pdb.get_image_tags_transaction(image.get_image())
+        .with_sauce(&sauce_link)
+        .execute()?;
+    if stale_tags.is_empty() || keep_tags {
+        return Ok(None);
+    }
+    let remove = remove_tags || common::confirm(&format!("Remove the {} tags found on the old sauce '{}' from '{}'?",
+        stale_tags.len(), sauce_link, image.get_image().get_filename()))?;
+    Ok(Some(sauce_link).filter(|_| remove))
+}
@@ -0,0 +1,109 @@
+use std::num::ParseIntError;
+use std::path::PathBuf;
+use colored::Colorize;
+use log::info;
+use pantsu_tags::{ImageInfo, PantsuTag, TagSuggestion};
+use pantsu_tags::db::PantsuDB;
+use crate::common::{AppResult, AppError};
+use crate::{common, CONFIGURATION};
+
+// only the most confident suggestions are offered for selection
+const MAX_SHOWN_SUGGESTIONS: usize = 20;
+
+enum Selection {
+    Tags(Vec<PantsuTag>),
+    SkipRemaining,
+}
+
+pub fn suggest_tags(image_paths: Vec<PathBuf>, neighbours: usize, threshold: Option<f32>) -> AppResult<()> {
+    let mut pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths)?;
+    if images.is_empty() {
+        println!("No images without tags to suggest tags for");
+        return Ok(());
+    }
+
+    let mut tagged = 0;
+    for image in images {
+        let image_name = image.get_image().get_filename();
+        let suggestions = pantsu_tags::suggest_tags(&pdb, image.get_image(), neighbours)?;
+        if suggestions.is_empty() {
+            println!("{} - {}", "No similar tagged images".yellow(), image_name);
+            continue;
+        }
+        let tags = match threshold {
+            Some(threshold) => suggestions.into_iter()
+                .filter(|suggestion| suggestion.confidence >= threshold)
+                .map(|suggestion| suggestion.tag)
+                .collect(),
+            None => match select_suggestions(image_name, &suggestions)? {
+                Selection::Tags(tags) => tags,
+                Selection::SkipRemaining => break,
+            },
+        };
+        if tags.is_empty() {
+            continue;
+        }
+        // the accepted suggestions count as tags of the user
+        pdb.update_images_transaction()
+            .for_image(image.get_image())
+            .add_tags(&tags)
+            .execute()?;
+        tagged += 1;
+        println!("Added {} tags to '{}'", tags.len(), image_name);
+        info!("Added suggested tags {} to image: '{}'", PantsuTag::display_vec(&tags), image_name);
+    }
+
+    println!("\n{} images tagged", tagged);
+    Ok(())
+}
+
+fn select_suggestions(image_name: &str, suggestions: &[TagSuggestion]) -> AppResult<Selection> {
+    let suggestions = &suggestions[..suggestions.len().min(MAX_SHOWN_SUGGESTIONS)];
+    println!("\n{}", image_name);
+    for (idx, suggestion) in suggestions.iter().enumerate() {
+        println!("  {} - {} ({:.0}%, {} similar images)", idx+1, suggestion.tag, suggestion.confidence * 100.0, suggestion.neighbours);
+    }
+    let stdin = std::io::stdin();
+    let mut input = String::new();
+    loop {
+        println!("Select the tags to add: (eg: \"1\", \"1 3 12\", \"a\" for all, empty for none, \"s\" to skip all remaining images)");
+        input.clear();
+        stdin.read_line(&mut input).map_err(AppError::StdinReadError)?;
+        let input = input.trim();
+        match input {
+            "a" => return Ok(Selection::Tags(suggestions.iter().map(|s| s.tag.clone()).collect())),
+            "s" => return Ok(Selection::SkipRemaining),
+            _ => {},
+        }
+        let input_numbers = input.split_whitespace()
+            .map(|num| num.parse::<usize>())
+            .collect::<Result<Vec<usize>, ParseIntError>>();
+        match input_numbers {
+            Ok(numbers) if numbers.iter().all(|&num| num >= 1 && num <= suggestions.len()) => {
+                return Ok(Selection::Tags(numbers.into_iter().map(|num| suggestions[num-1].tag.clone()).collect()));
+            },
+            Ok(_) => println!("Invalid input: tag numbers must be in range 1 to {}", suggestions.len()),
+            Err(_) => println!("Invalid input"),
+        }
+    }
+}
+
+fn get_images(pdb: &PantsuDB, image_paths: &[PathBuf]) -> AppResult<Vec<ImageInfo>> {
+    if image_paths.is_empty() {
+        let mut untagged = Vec::new();
+        for image in pdb.get_images_transaction().execute()? {
+            if pdb.get_image_tags_transaction(image.get_image()).execute()?.is_empty() {
+                untagged.push(image);
+            }
+        }
+        return Ok(untagged);
+    }
+    image_paths.iter()
+        .map(|path| {
+            let image_handle = common::image_handle_from_path(path)?;
+            pdb.get_image_transaction(&image_handle).execute()?
+                .ok_or_else(|| AppError::ImageNotFound(image_handle.get_filename().to_string()))
+        })
+        .collect()
+}
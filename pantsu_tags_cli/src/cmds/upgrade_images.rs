@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use colored::Colorize;
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use pantsu_tags::{ImageInfo, Sauce, SauceJudgePolicy, SaucePost, TmpFile};
+use pantsu_tags::db::PantsuDB;
+use crate::common::{AppResult, AppError};
+use crate::{common, CONFIGURATION, sauce_providers};
+
+enum Upgrade {
+    NotLarger,
+    Available(SaucePost),
+    Downloaded(SaucePost, TmpFile, f32),
+}
+
+pub fn upgrade_images(image_paths: Vec<PathBuf>, dry_run: bool, policy: &SauceJudgePolicy) -> AppResult<()> {
+    let mut pdb = CONFIGURATION.open_db()?;
+    let images = get_images(&pdb, &image_paths)?
+        .into_iter()
+        .filter_map(|image| match image.get_sauce() {
+            Sauce::Match(url) => {
+                let link = url.to_string();
+                Some((image, link))
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        println!("No images with a sauce to upgrade");
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(pantsu_tags::Error::TokioInitError)?;
+    let upgrades = rt.block_on(stream::iter(images)
+        .map(|(image, link)| async move {
+            let upgrade = find_upgrade(&image, &link, dry_run).await;
+            (image, upgrade)
+        })
+        .buffer_unordered(policy.max_concurrent_lookups)
+        .collect::<Vec<_>>());
+
+    let (mut upgraded, mut rejected, mut failed) = (0, 0, 0);
+    for (image, upgrade) in upgrades {
+        let image_name = image.get_image().get_filename();
+        let (width, height) = image.get_res();
+        match upgrade {
+            Ok(Upgrade::NotLarger) => {},
+            Ok(Upgrade::Available(post)) => {
+                upgraded += 1;
+                println!("{} - {}: {}x{} -> {}x{}", "Can be upgraded".green(), image_name, width, height, post.resolution.0, post.resolution.1);
+            },
+            Ok(Upgrade::Downloaded(post, _, confidence)) if confidence < policy.accept_confidence => {
+                rejected += 1;
+                println!("{} - {}: confidence {:.2}", "Original differs".yellow(), image_name, confidence);
+                warn!("Rejected original '{}' of image '{}' with confidence {}", post.file_url.unwrap_or_default(), image_name, confidence);
+            },
+            Ok(Upgrade::Downloaded(post, original, _)) => {
+                match pantsu_tags::replace_image(&mut pdb, &CONFIGURATION.library_path, image.get_image(), original.get_path()) {
+                    Ok(new_image) => {
+                        upgraded += 1;
+                        println!("{} - {}: {}x{} -> {}x{}", "Upgraded image".green(), new_image.get_filename(), width, height, post.resolution.0, post.resolution.1);
+                        info!("Upgraded image '{}' to '{}' from '{}'", image_name, new_image.get_filename(), post.file_url.unwrap_or_default());
+                    },
+                    Err(e) => {
+                        failed += 1;
+                        println!("{} - {}: {}", "Failed to replace image".red(), image_name, e);
+                        warn!("Failed to replace image '{}' by its original '{}': {}", image_name, post.file_url.unwrap_or_default(), e);
+                    },
+                }
+            },
+            Err(e) => {
+                failed += 1;
+                println!("{} - {}: {}", "Failed to upgrade image".red(), image_name, e);
+                warn!("Failed to upgrade image '{}': {}", image_name, e);
+            },
+        }
+    }
+
+    if dry_run {
+        println!("\n{} images can be upgraded, {} failed (dry run, nothing changed)", upgraded, failed);
+    } else {
+        println!("\n{} images upgraded, {} originals rejected, {} failed", upgraded, rejected, failed);
+    }
+    Ok(())
+}
+
+// only downloads the original if it has more pixels than the local image
+async fn find_upgrade(image: &ImageInfo, sauce_link: &str, dry_run: bool) -> AppResult<Upgrade> {
+    let providers = sauce_providers()?;
+    let post = pantsu_tags::get_sauce_post(providers, sauce_link).await?;
+    let (width, height) = image.get_res();
+    let original_pixels = post.resolution.0.max(0) as u64 * post.resolution.1.max(0) as u64;
+    if original_pixels <= width as u64 * height as u64 {
+        return Ok(Upgrade::NotLarger);
+    }
+    if dry_run {
+        return Ok(Upgrade::Available(post));
+    }
+    let original = pantsu_tags::download_sauce_original(providers, sauce_link, &post).await?;
+    let confidence = pantsu_tags::image_similarity::get_original_confidence(image, original.get_path())?;
+    Ok(Upgrade::Downloaded(post, original, confidence))
+}
+
+fn get_images(pdb: &PantsuDB, image_paths: &[PathBuf]) -> AppResult<Vec<ImageInfo>> {
+    if image_paths.is_empty() {
+        return Ok(pdb.get_images_transaction().with_existing_sauce().execute()?);
+    }
+    image_paths.iter()
+        .map(|path| {
+            let image_handle = common::image_handle_from_path(path)?;
+            pdb.get_image_transaction(&image_handle).execute()?
+                .ok_or_else(|| AppError::ImageNotFound(image_handle.get_filename().to_string()))
+        })
+        .collect()
+}
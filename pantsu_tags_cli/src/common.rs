@@ -1,7 +1,12 @@
-use std::{path::Path, str::FromStr};
-use pantsu_tags::{Error, ImageHandle, db::sort::{SortOrder, ImageSortOption, TagSortOption}};
+use std::{path::Path, str::FromStr, time::Duration};
+use colored::Colorize;
+use log::warn;
+use pantsu_tags::{Error, ImageHandle, PantsuTag, PantsuTagType, db::PantsuDB, db::sort::{SortOrder, ImageSortOption, TagSortOption}};
 use tokio::task::JoinError;
 
+// closest existing tags shown for a tag that does not exist
+const MAX_SIMILAR_TAGS: usize = 3;
+
 pub type AppResult<T> = std::result::Result<T, AppError>;
 
 pub fn get_path(path: &Path) -> String {
@@ -29,6 +34,43 @@ pub fn image_handle_from_path(path: &Path) -> AppResult<ImageHandle> {
     Ok(image_handle)
 }
 
+// parses the tags of a query, text without a valid tag type is a general tag.
+// Warns about tags that do not exist, since they never match any image
+pub fn parse_query_tags(pdb: &PantsuDB, tags: &[String]) -> AppResult<Vec<PantsuTag>> {
+    let existing_tags = pdb.get_tags_transaction().execute()?;
+    tags.iter()
+        .map(|text| {
+            let tag = PantsuTag::from_str(text).unwrap_or_else(|_| PantsuTag::new(text.to_string(), PantsuTagType::General));
+            if !existing_tags.contains(&tag) {
+                warn_unknown_tag(pdb, text)?;
+            }
+            Ok(tag)
+        })
+        .collect()
+}
+
+pub fn warn_unknown_tag(pdb: &PantsuDB, text: &str) -> AppResult<()> {
+    let similar_tags = pdb.get_similar_tags_transaction(text)
+        .limit(MAX_SIMILAR_TAGS)
+        .execute()?;
+    warn!("Tag '{}' does not exist", text);
+    if similar_tags.is_empty() {
+        eprintln!("{} tag '{}' does not exist", "Warning:".yellow(), text);
+    } else {
+        let similar_tags = similar_tags.iter().map(|tag| tag.to_string()).collect::<Vec<String>>();
+        eprintln!("{} tag '{}' does not exist, did you mean {}?", "Warning:".yellow(), text, similar_tags.join(", "));
+    }
+    Ok(())
+}
+
+// asks a yes/no question on stdin, anything but 'y' counts as no
+pub fn confirm(question: &str) -> AppResult<bool> {
+    println!("{} [y/N]", question);
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).map_err(AppError::StdinReadError)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
 pub fn parse_image_sort_order(options: Vec<String>) -> AppResult<Option<SortOrder<ImageSortOption>>> {
     let options = options.iter()
         .map(|o| ImageSortOption::from_str(o).or_else(|e| Err(AppError::LibError(e))))
@@ -51,6 +93,23 @@ pub fn parse_tag_sort_order(options: Vec<String>) -> AppResult<Option<SortOrder<
     }
 }
 
+// parses durations like '90s', '30m', '12h', '7d' or '2w'
+pub fn parse_duration(text: &str) -> AppResult<Duration> {
+    let text = text.trim();
+    let unit_start = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (value, unit) = text.split_at(unit_start);
+    let value = value.parse::<u64>().map_err(|_| AppError::InvalidDuration(text.to_string()))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(AppError::InvalidDuration(text.to_string())),
+    };
+    Ok(Duration::from_secs(value.saturating_mul(unit_secs)))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Image not found in PantsuTags: {0}")]
@@ -68,6 +127,9 @@ pub enum AppError {
     #[error("Invalid path: unable to convert path to string")]
     PathConversionError,
 
+    #[error("Invalid duration '{0}', expected a number followed by s, m, h, d or w, e.g. '30d'")]
+    InvalidDuration(String),
+
     #[error("Faild to join with task")]
     TaskJoinError(#[from] JoinError),
 
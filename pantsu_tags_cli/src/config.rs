@@ -3,13 +3,25 @@ use directories::{BaseDirs};
 use figment::{Figment};
 use figment::providers::{Format, Serialized, Yaml};
 use serde_derive::{Deserialize,Serialize};
+use pantsu_tags::{CacheConfig, GelbooruCredentials, ModelTaggerConfig, SauceConfig, SauceJudgePolicy, SauceProviders, Rating, TagNormalizer};
+use pantsu_tags::db::PantsuDB;
 use crate::AppError;
+use crate::common::AppResult;
 
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct AppConfig {
     pub library_path: PathBuf,
     pub database_path: PathBuf,
-    pub log_path: PathBuf
+    pub log_path: PathBuf,
+    pub gelbooru_api_key: Option<String>,
+    pub gelbooru_user_id: Option<String>,
+    pub sauce: SauceConfig,
+    pub sauce_judge: SauceJudgePolicy,
+    pub tagger: ModelTaggerConfig,
+    pub tag_normalizer: TagNormalizer,
+    // images rated above this are left out of image selections, e.g. at work.
+    // Images without a rating are left out as well while it is set
+    pub max_rating: Option<Rating>,
 }
 
 impl AppConfig {
@@ -23,6 +35,19 @@ impl AppConfig {
             .or_else(|e|Err(AppError::ConfigError(e)))
             .unwrap()
     }
+
+    pub fn sauce_providers(&self) -> AppResult<SauceProviders> {
+        let gelbooru_credentials = match (&self.gelbooru_api_key, &self.gelbooru_user_id) {
+            (Some(api_key), Some(user_id)) => Some(GelbooruCredentials { api_key: api_key.clone(), user_id: user_id.clone() }),
+            _ => None,
+        };
+        Ok(SauceProviders::from_config(&self.sauce, gelbooru_credentials)?)
+    }
+
+    // image selections of the database leave out images rated above max_rating unless a command overrides it
+    pub fn open_db(&self) -> AppResult<PantsuDB> {
+        Ok(PantsuDB::new(self.database_path.as_path())?.with_max_rating(self.max_rating))
+    }
 }
 
 impl Default for AppConfig {
@@ -30,7 +55,20 @@ impl Default for AppConfig {
         AppConfig {
             library_path: PathBuf::from("./test_image_lib"), //file_handler::default_lib_dir(),
             database_path: PathBuf::from("./pantsu_tags.db"), //file_handler::default_db_dir()
-            log_path: PathBuf::from("./pantsu_tags.log")
+            log_path: PathBuf::from("./pantsu_tags.log"),
+            gelbooru_api_key: None,
+            gelbooru_user_id: None,
+            sauce: SauceConfig {
+                cache: CacheConfig {
+                    directory: Some(PathBuf::from("./pantsu_tags_cache")),
+                    ..CacheConfig::default()
+                },
+                ..SauceConfig::default()
+            },
+            sauce_judge: SauceJudgePolicy::default(),
+            tagger: ModelTaggerConfig::default(),
+            tag_normalizer: TagNormalizer::default(),
+            max_rating: None,
         }
     }
 }
\ No newline at end of file
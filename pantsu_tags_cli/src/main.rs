@@ -1,11 +1,13 @@
 use ::log::{error, info, LevelFilter};
+use std::sync::OnceLock;
 use clap::Parser;
 use lazy_static::lazy_static;
 
+use pantsu_tags::{Rating, SauceJudgePolicy, SauceProviders};
 use pantsu_tags::db::AspectRatio;
 
-use crate::cli::{Args};
-use crate::common::AppError;
+use crate::cli::{Args, CacheCommand};
+use crate::common::{AppError, AppResult};
 use crate::config::AppConfig;
 
 mod common;
@@ -19,9 +21,29 @@ lazy_static! {
     pub static ref CONFIGURATION: AppConfig = AppConfig::load_config();
 }
 
+static SAUCE_PROVIDERS: OnceLock<SauceProviders> = OnceLock::new();
+
+// created on first use, so commands without network access don't fail on a broken proxy config
+pub fn sauce_providers() -> AppResult<&'static SauceProviders> {
+    if let Some(providers) = SAUCE_PROVIDERS.get() {
+        return Ok(providers);
+    }
+    let providers = CONFIGURATION.sauce_providers()?;
+    Ok(SAUCE_PROVIDERS.get_or_init(|| providers))
+}
+
+// the max rating given to a command overrides the one of the config
+fn max_rating(max_rating: Option<Rating>, all_ratings: bool) -> Option<Rating> {
+    match all_ratings {
+        true => None,
+        false => max_rating.or(CONFIGURATION.max_rating),
+    }
+}
+
 fn main() -> Result<(), AppError> {
     log4rs::init_config(log::log_config(LevelFilter::Info)).unwrap();
     let args = Args::parse();
+    pantsu_tags::set_tag_normalizer(CONFIGURATION.tag_normalizer.clone());
     //println!("Got arguments {:?}", args);
     let res: Result<(), AppError> = match args {
         Args::ImportImages(args) => {
@@ -42,11 +64,23 @@ fn main() -> Result<(), AppError> {
         },
         Args::ListTags(args) => {
             info!("Running command 'list-tags'");
-            cmds::list_tags(args.images, args.tag_types, args.sort_order, args.print_tagnames)
+            cmds::list_tags(args.images, args.tag_types, args.sort_order, args.print_tagnames, args.counts)
+        },
+        Args::RelatedTags(args) => {
+            info!("Running command 'related-tags'");
+            cmds::related_tags(args.tags, args.tag_types, args.limit)
+        },
+        Args::SearchTags(args) => {
+            info!("Running command 'search-tags'");
+            cmds::search_tags(&args.pattern, args.tag_types, args.print_tagnames)
+        },
+        Args::NormalizeTags => {
+            info!("Running command 'normalize-tags'");
+            cmds::normalize_tags()
         },
         Args::ImageInfos(args) => {
             info!("Running command 'image-infos'");
-            cmds::image_infos(args.images, args.sort_order)
+            cmds::image_infos(args.images, args.sort_order, max_rating(args.max_rating, args.all_ratings))
         },
         Args::ListImages(args) => {
             info!("Running command 'list-images'");
@@ -55,11 +89,20 @@ fn main() -> Result<(), AppError> {
                 (Some(min), None) => AspectRatio::Min(min),
                 (None, Some(max)) => AspectRatio::Max(max),
                 (None, None) => AspectRatio::Any,
-            }, args.print_filenames, args.sauce_existing, args.sauce_not_existing, args.sauce_not_checked, args.sort_order, None)
+            }, args.print_filenames, args.sauce_existing, args.sauce_not_existing, args.sauce_not_checked, args.sauce_unsure, args.sort_order, max_rating(args.max_rating, args.all_ratings), None)
         },
         Args::AutoLookupTags(args) => {
             info!("Running command 'auto-lookup-tags'");
-            cmds::auto_lookup_tags(args.images, args.sauce_existing, args.sauce_not_existing, args.sauce_not_checked, args.no_feh)
+            let policy = SauceJudgePolicy {
+                found_similarity: args.found_similarity.unwrap_or(CONFIGURATION.sauce_judge.found_similarity),
+                relevant_similarity: args.relevant_similarity.unwrap_or(CONFIGURATION.sauce_judge.relevant_similarity),
+                max_concurrent_lookups: args.max_concurrent_lookups.unwrap_or(CONFIGURATION.sauce_judge.max_concurrent_lookups),
+                auto_judge: CONFIGURATION.sauce_judge.auto_judge && !args.no_auto_judge,
+                accept_confidence: args.accept_confidence.unwrap_or(CONFIGURATION.sauce_judge.accept_confidence),
+                reject_confidence: args.reject_confidence.unwrap_or(CONFIGURATION.sauce_judge.reject_confidence),
+                ..CONFIGURATION.sauce_judge.clone()
+            };
+            cmds::auto_lookup_tags(args.images, args.sauce_existing, args.sauce_not_existing, args.sauce_not_checked, args.recheck_older_than, args.no_feh, &policy)
         },
         Args::ImportTags(args) => {
             info!("Running command 'import-tags'");
@@ -68,7 +111,50 @@ fn main() -> Result<(), AppError> {
         Args::ExportTags(args) => {
             info!("Running command 'export-tags'");
             cmds::export_tags(&args.file)
-        }
+        },
+        Args::MergeImages(args) => {
+            info!("Running command 'merge-images'");
+            cmds::merge_images(&args.keep, &args.discard, args.keep_alias)
+        },
+        Args::ReviewSauces(args) => {
+            info!("Running command 'review-sauces'");
+            cmds::review_sauces(args.no_feh, &CONFIGURATION.sauce_judge)
+        },
+        Args::RefreshTags(args) => {
+            info!("Running command 'refresh-tags'");
+            cmds::refresh_tags(args.images, &args.include_tags, &args.exclude_tags, args.older_than_days, args.dry_run)
+        },
+        Args::CheckSauces(args) => {
+            info!("Running command 'check-sauces'");
+            cmds::check_sauces(args.images, args.fallback, &CONFIGURATION.sauce_judge)
+        },
+        Args::UpgradeImages(args) => {
+            info!("Running command 'upgrade-images'");
+            cmds::upgrade_images(args.images, args.dry_run, &CONFIGURATION.sauce_judge)
+        },
+        Args::SetSauce(args) => {
+            info!("Running command 'set-sauce'");
+            cmds::set_sauce(&args.image, &args.url, args.remove_tags, args.keep_tags)
+        },
+        Args::ClearSauce(args) => {
+            info!("Running command 'clear-sauce'");
+            cmds::clear_sauce(args.images, args.remove_tags, args.keep_tags)
+        },
+        Args::SuggestTags(args) => {
+            info!("Running command 'suggest-tags'");
+            cmds::suggest_tags(args.images, args.neighbours, args.threshold)
+        },
+        #[cfg(feature = "ai-tagger")]
+        Args::AiTag(args) => {
+            info!("Running command 'ai-tag'");
+            cmds::ai_tag(args.images, args.dry_run, &CONFIGURATION.tagger)
+        },
+        Args::Cache(args) => match args.command {
+            CacheCommand::Clear => {
+                info!("Running command 'cache clear'");
+                cmds::clear_cache()
+            },
+        },
     };
 
     match res {